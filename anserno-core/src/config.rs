@@ -1,16 +1,47 @@
 use actix_web::web;
 
-use crate::handlers::{api, authors, books, index, search, series, static_files};
+use crate::{
+    context::Context,
+    handlers::{
+        api, authors, books, index, libraries, publishers, search, series, shelves, static_files,
+        tags,
+    },
+};
 
-pub fn configure(config: &mut web::ServiceConfig) {
+/// Routes of the library held by the `Context` of the enclosing scope.
+pub fn configure_library(config: &mut web::ServiceConfig) {
     config
         .service(api::service())
         .service(authors::service())
         .service(books::service())
+        .service(publishers::service())
         .service(search::service())
         .service(series::service())
+        .service(shelves::service())
+        .service(tags::service())
+        .service(index::service());
+}
+
+/// Routes of the default library, served at the root.
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
         .service(static_files::service())
-        .service(index::get)
+        .service(libraries::service())
+        .configure(configure_library)
         .service(index::get_robots_txt)
         .default_service(web::to(index::default_service));
 }
+
+/// Mount each library under its `/l/{name}` scope with its own `Context`,
+/// followed by the default library at the root.
+pub fn configure_libraries(config: &mut web::ServiceConfig, contexts: &[Context]) {
+    for ctx in contexts {
+        config.service(
+            web::scope(ctx.url_prefix())
+                .app_data(web::Data::new(ctx.clone()))
+                .configure(configure_library),
+        );
+    }
+
+    configure(config);
+}
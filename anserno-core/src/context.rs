@@ -1,24 +1,61 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use calibre_data::{library::LibraryBackend, search::SearchWeights};
+
+use crate::{
+    identifier_links::IdentifierLinks, libraries::Libraries, shared_library::SharedLibrary,
+};
 
 #[derive(::std::clone::Clone, ::std::fmt::Debug, derive_builder::Builder)]
 pub struct Context {
-    library: calibre_data::library::RemoteLibrary,
+    #[builder(setter(into))]
+    library: SharedLibrary,
     template_engine: tera::Tera,
 
     #[builder(setter(into))]
     static_files_dir: PathBuf,
+
+    #[builder(default)]
+    identifier_links: IdentifierLinks,
+
+    #[builder(setter(into), default)]
+    library_name: String,
+
+    /// Prefix of the links rendered for this library, empty at the root
+    #[builder(setter(into), default)]
+    url_prefix: String,
+
+    #[builder(default)]
+    libraries: Libraries,
+
+    /// Whether the `/api/admin` endpoints are served
+    #[builder(default)]
+    admin: bool,
+
+    /// Column weights ranking full text search hits
+    #[builder(default)]
+    search_weights: SearchWeights,
 }
 
 impl Context {
     pub fn new(
-        library: calibre_data::library::RemoteLibrary,
+        library: impl Into<SharedLibrary>,
         template_engine: tera::Tera,
         static_files_dir: impl Into<PathBuf>,
     ) -> Self {
         Self {
-            library,
-            template_engine,
+            library: library.into(),
+            template_engine: Libraries::default().template_engine(&template_engine, ""),
             static_files_dir: static_files_dir.into(),
+            identifier_links: IdentifierLinks::default(),
+            library_name: String::default(),
+            url_prefix: String::default(),
+            libraries: Libraries::default(),
+            admin: false,
+            search_weights: SearchWeights::default(),
         }
     }
 
@@ -27,8 +64,14 @@ impl Context {
         &self.template_engine
     }
 
+    /// Snapshot of the library, unaffected by refreshes while held.
+    #[inline]
+    pub fn library(&self) -> Arc<dyn LibraryBackend> {
+        self.library.load()
+    }
+
     #[inline]
-    pub fn library(&self) -> &calibre_data::library::RemoteLibrary {
+    pub fn shared_library(&self) -> &SharedLibrary {
         &self.library
     }
 
@@ -36,4 +79,34 @@ impl Context {
     pub fn static_files_dir(&self) -> &Path {
         &self.static_files_dir
     }
+
+    #[inline]
+    pub fn identifier_links(&self) -> &IdentifierLinks {
+        &self.identifier_links
+    }
+
+    #[inline]
+    pub fn library_name(&self) -> &str {
+        &self.library_name
+    }
+
+    #[inline]
+    pub fn url_prefix(&self) -> &str {
+        &self.url_prefix
+    }
+
+    #[inline]
+    pub fn libraries(&self) -> &Libraries {
+        &self.libraries
+    }
+
+    #[inline]
+    pub fn admin(&self) -> bool {
+        self.admin
+    }
+
+    #[inline]
+    pub fn search_weights(&self) -> &SearchWeights {
+        &self.search_weights
+    }
 }
@@ -6,6 +6,7 @@ pub use json_response_error::*;
 
 #[derive(Debug)]
 pub enum Error {
+    BadRequest(String),
     CalibreData(calibre_data::error::Error),
     DbErr(sea_orm::DbErr),
     Forbidden(String),
@@ -21,6 +22,7 @@ impl ::std::fmt::Display for Error {
         write!(f, "anserno-core: ")?;
 
         match self {
+            Self::BadRequest(msg) => write!(f, "BadRequest: {msg}"),
             Self::CalibreData(err) => err.fmt(f),
             Self::DbErr(err) => err.fmt(f),
             Self::Forbidden(msg) => write!(f, "Forbidden: {msg}"),
@@ -36,6 +38,7 @@ impl ::std::fmt::Display for Error {
 impl crate::error::StatusCode for Error {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
+            Self::BadRequest(_) => actix_web::http::StatusCode::BAD_REQUEST,
             Self::Forbidden(_) => actix_web::http::StatusCode::FORBIDDEN,
             Self::NotFound(_) => actix_web::http::StatusCode::NOT_FOUND,
             Self::CalibreData(_)
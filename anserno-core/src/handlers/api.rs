@@ -1,18 +1,23 @@
 use actix_web::{web, HttpResponse, Responder};
 use calibre_data::{
-    entities::{authors, books, series},
-    library::CalibreLibrary,
+    annotations::{Annotation, ReadingPosition},
+    entities::{authors, books, identifiers, publishers, search_index, series, tags},
+    integrity::IntegrityReport,
     pagination::{QueryPaginator, RecordsQuery},
+    query::language_filter::LanguageFilter,
+    search::{SearchHighlight, Suggestion, SuggestionKind},
+    shelves::Shelves,
 };
 use hypertext_application_language::{ext::sea_orm::AsResource, link::Link, resource::Resource};
 use pagination::{config::Config, paginator::Paginator};
 
-use sea_orm::{EntityTrait, PrimaryKeyTrait};
+use sea_orm::{EntityTrait, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect};
 
 use crate::{
     context::Context,
     error::{Error, JsonResponseResult, ToJsonError},
-    url_params::Pagination,
+    handlers::{search::search_query, shelves::shelf_id},
+    url_params::{Language, Pagination, Search, Suggest},
 };
 
 #[actix_web::get("")]
@@ -35,6 +40,21 @@ pub async fn get_root() -> impl Responder {
                     Link::new("/books/{id}")
                         .with_title("book")
                         .with_templated(true),
+                    Link::new("/books/{id}/annotations")
+                        .with_title("book annotations")
+                        .with_templated(true),
+                    Link::new("/books/by-identifier/{type}/{value}")
+                        .with_title("book by identifier")
+                        .with_templated(true),
+                ],
+            )
+            .with_links(
+                "publishers",
+                [
+                    Link::new("/publishers").with_title("publishers"),
+                    Link::new("/publishers/{id}")
+                        .with_title("publisher")
+                        .with_templated(true),
                 ],
             )
             .with_links(
@@ -45,6 +65,38 @@ pub async fn get_root() -> impl Responder {
                         .with_title("series")
                         .with_templated(true),
                 ],
+            )
+            .with_links(
+                "search",
+                [
+                    Link::new("/search?query={query}")
+                        .with_title("full text search")
+                        .with_templated(true),
+                    Link::new("/search?calibre={expression}")
+                        .with_title("calibre search")
+                        .with_templated(true),
+                    Link::new("/suggest?q={input}")
+                        .with_title("search suggestions")
+                        .with_templated(true),
+                ],
+            )
+            .with_links(
+                "shelves",
+                [
+                    Link::new("/shelves").with_title("shelves"),
+                    Link::new("/shelves/{name}")
+                        .with_title("shelf")
+                        .with_templated(true),
+                ],
+            )
+            .with_links(
+                "tags",
+                [
+                    Link::new("/tags").with_title("tags"),
+                    Link::new("/tags/{id}")
+                        .with_title("tag")
+                        .with_templated(true),
+                ],
             ),
     )
 }
@@ -52,15 +104,19 @@ pub async fn get_root() -> impl Responder {
 pub async fn get<E>(
     ctx: web::Data<Context>,
     pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
 ) -> JsonResponseResult<impl Responder>
 where
-    E: EntityTrait,
+    E: EntityTrait + LanguageFilter,
     <E as EntityTrait>::Model: ::core::marker::Sync + AsResource,
 {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
     let Pagination { items, page } = pagination.into_inner();
 
-    let paginator = QueryPaginator::from_query(conn, E::find())
+    let query = E::filter_language_opt(E::find(), language.lang.as_deref());
+
+    let paginator = QueryPaginator::from_query(conn, query)
         .await
         .map_err(ToJsonError::to_json_error)?
         .with_page_length(items);
@@ -112,7 +168,8 @@ where
     <E as EntityTrait>::Model: ::core::marker::Sync + AsResource,
     <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType: From<i32>,
 {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
 
     let id = id.into_inner();
 
@@ -134,7 +191,7 @@ where
 
 pub fn entity_service<E>(name: &str) -> actix_web::Scope
 where
-    E: EntityTrait,
+    E: EntityTrait + LanguageFilter,
     <E as EntityTrait>::Model: ::core::marker::Sync + AsResource,
     <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType: From<i32>,
 {
@@ -143,17 +200,426 @@ where
         .service(web::resource(["/{id}"]).route(web::get().to(get_id::<E>)))
 }
 
-pub async fn api_redirect() -> impl Responder {
+#[derive(serde::Deserialize)]
+pub struct IdentifierRequest {
+    kind: String,
+    value: String,
+}
+
+#[actix_web::get("/books/by-identifier/{kind}/{value}")]
+pub async fn get_book_by_identifier(
+    ctx: web::Data<Context>,
+    identifier_request: web::Path<IdentifierRequest>,
+) -> JsonResponseResult<impl Responder> {
+    let IdentifierRequest { kind, value } = identifier_request.into_inner();
+
+    let identifier = identifiers::Entity::find_by_kind_value(&kind, &value)
+        .one(ctx.library().conn())
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .ok_or(Error::NotFound(format!(
+            "No record found for Identifier({kind}={value})"
+        )))
+        .map_err(ToJsonError::to_json_error)?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((
+            "location",
+            format!("{}/api/books/{}", ctx.url_prefix(), identifier.book),
+        ))
+        .finish())
+}
+
+#[actix_web::get("/books/{id}")]
+pub async fn get_book(
+    ctx: web::Data<Context>,
+    id: web::Path<i32>,
+) -> JsonResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let id = id.into_inner();
+
+    let book = books::Entity::find_by_id(id)
+        .one(conn)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .ok_or(Error::NotFound(format!(
+            "No record found for Book(id={id})"
+        )))
+        .map_err(ToJsonError::to_json_error)?;
+
+    let custom_fields = library
+        .custom_columns()
+        .book_fields(conn, book.id)
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    book.as_resource(conn)
+        .await
+        .map(|resource| {
+            web::Json(
+                resource.with_property(
+                    "custom_columns",
+                    custom_fields
+                        .into_iter()
+                        .map(|field| (field.label, field.value.into()))
+                        .collect::<serde_json::Map<_, _>>(),
+                ),
+            )
+        })
+        .map_err(ToJsonError::to_json_error)
+}
+
+#[actix_web::get("/books/{id}/annotations")]
+pub async fn get_book_annotations(
+    ctx: web::Data<Context>,
+    id: web::Path<i32>,
+) -> JsonResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let id = id.into_inner();
+
+    let book = books::Entity::find_by_id(id)
+        .one(conn)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .ok_or(Error::NotFound(format!(
+            "No record found for Book(id={id})"
+        )))
+        .map_err(ToJsonError::to_json_error)?;
+
+    let annotations = Annotation::find_by_book(conn, book.id)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .into_iter()
+        .map(|annotation| {
+            Resource::default()
+                .with_property("id", annotation.id)
+                .with_property("kind", annotation.kind)
+                .with_property("format", annotation.format)
+                .with_property("user", annotation.user)
+                .with_property("text", annotation.text)
+                .with_property("notes", annotation.notes)
+                .with_property("chapter", annotation.chapter)
+                .with_property("timestamp", serde_json::json!(annotation.timestamp))
+        })
+        .collect::<Vec<_>>();
+
+    let reading_positions = ReadingPosition::find_by_book(conn, book.id)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .into_iter()
+        .map(|position| {
+            Resource::default()
+                .with_property("format", position.format)
+                .with_property("user", position.user)
+                .with_property("device", position.device)
+                .with_property("progress", position.progress)
+                .with_property("timestamp", serde_json::json!(position.timestamp))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(web::Json(
+        Resource::default()
+            .with_link("self", Link::new(format!("/books/{}/annotations", book.id)))
+            .with_link("book", book.self_link().with_title(&book.title))
+            .with_property("count", annotations.len())
+            .with_embeddeds("items", annotations)
+            .with_embeddeds("reading_positions", reading_positions),
+    ))
+}
+
+#[actix_web::get("/shelves")]
+pub async fn get_shelves(ctx: web::Data<Context>) -> JsonResponseResult<impl Responder> {
+    let shelves = Shelves::load(ctx.library().conn())
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    let items = shelves
+        .iter()
+        .map(|shelf| {
+            Resource::default()
+                .with_link(
+                    "self",
+                    Link::new(format!("/shelves/{}", shelf_id(&shelf.name)))
+                        .with_title(&shelf.name),
+                )
+                .with_property("name", shelf.name.as_str())
+                .with_property("kind", serde_json::json!(shelf.kind))
+                .with_property("search", shelf.search.as_str())
+        })
+        .collect::<Vec<_>>();
+
+    Ok(web::Json(
+        Resource::default()
+            .with_link("self", Link::new("/shelves"))
+            .with_property("count", items.len())
+            .with_embeddeds("items", items),
+    ))
+}
+
+#[actix_web::get("/shelves/{name}")]
+pub async fn get_shelf(
+    ctx: web::Data<Context>,
+    name: web::Path<String>,
+    pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
+) -> JsonResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let name = name.into_inner();
+
+    let shelves = Shelves::load(conn)
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    let shelf = shelves
+        .get(&name)
+        .ok_or(Error::NotFound(format!(
+            "No record found for Shelf(name={name})"
+        )))
+        .map_err(ToJsonError::to_json_error)?;
+
+    let condition = shelves
+        .condition(shelf, books::Column::Id, library.custom_columns())
+        .map_err(|err| Error::BadRequest(err.to_string()).to_json_error())?;
+
+    let Pagination { items, page } = pagination.into_inner();
+
+    let query = books::Entity::filter_language_opt(
+        books::Entity::find().filter(condition),
+        language.lang.as_deref(),
+    )
+    .order_by_asc(books::Column::Sort);
+
+    let paginator = QueryPaginator::from_query(conn, query)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .with_page_length(items);
+
+    let records = paginator
+        .records_query(page)
+        .all(conn)
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    let shelf_link = |page| {
+        Link::new(format!(
+            "/shelves/{}?page={page}&items={items}",
+            shelf_id(&shelf.name)
+        ))
+    };
+
+    let mut resource = Resource::default().with_link("self", shelf_link(page));
+
+    let paginator_page = paginator.page(page);
+
+    if let Some(prev) = paginator_page.previous() {
+        resource = resource.with_link("prev", shelf_link(*prev));
+    }
+
+    if let Some(next) = paginator_page.next() {
+        resource = resource.with_link("next", shelf_link(*next));
+    }
+
+    Ok(web::Json(
+        resource
+            .with_property("name", shelf.name.as_str())
+            .with_property("kind", serde_json::json!(shelf.kind))
+            .with_property("search", shelf.search.as_str())
+            .with_property("page", page)
+            .with_property("pages", Config::last(paginator.config()))
+            .with_property("count", records.len())
+            .with_embeddeds(
+                "items",
+                records
+                    .iter()
+                    .map(Resource::from_model::<books::Entity>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ToJsonError::to_json_error)?,
+            ),
+    ))
+}
+
+#[actix_web::get("/search")]
+pub async fn get_search(
+    ctx: web::Data<Context>,
+    search: web::Query<Search>,
+    pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
+) -> JsonResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let search = search.into_inner();
+
+    let (query, full_text_query) = search_query(
+        conn,
+        library.custom_columns(),
+        &search,
+        ctx.search_weights(),
+    )
+    .await
+    .map_err(ToJsonError::to_json_error)?;
+
+    let Pagination { items, page } = pagination.into_inner();
+
+    let query = search_index::Entity::filter_language_opt(query, language.lang.as_deref())
+        .select_only()
+        .column(search_index::Column::BookId);
+
+    let paginator = QueryPaginator::from_query(conn, query)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .with_page_length(items);
+
+    let records = paginator
+        .records_query(page)
+        .find_with_related(books::Entity)
+        .all(conn)
+        .await
+        .map_err(ToJsonError::to_json_error)?
+        .into_iter()
+        .flat_map(|(_, books)| books)
+        .collect::<Vec<_>>();
+
+    let mut highlights = match &full_text_query {
+        Some(full_text_query) => SearchHighlight::find(
+            conn,
+            full_text_query,
+            &records.iter().map(|book| book.id).collect::<Vec<_>>(),
+        )
+        .await
+        .map_err(ToJsonError::to_json_error)?,
+        None => Default::default(),
+    };
+
+    let search_link = |page| {
+        Link::new(format!(
+            "{}&page={page}&items={items}",
+            search.url("/search")
+        ))
+    };
+
+    let mut resource = Resource::default().with_link("self", search_link(page));
+
+    let paginator_page = paginator.page(page);
+
+    if let Some(prev) = paginator_page.previous() {
+        resource = resource.with_link("prev", search_link(*prev));
+    }
+
+    if let Some(next) = paginator_page.next() {
+        resource = resource.with_link("next", search_link(*next));
+    }
+
+    Ok(web::Json(
+        resource
+            .with_property("page", page)
+            .with_property("pages", Config::last(paginator.config()))
+            .with_property("count", records.len())
+            .with_embeddeds(
+                "items",
+                records
+                    .iter()
+                    .map(|book| {
+                        Resource::from_model::<books::Entity>(book).map(|resource| match highlights
+                            .remove(&book.id)
+                        {
+                            Some(highlight) => {
+                                resource.with_property("highlight", serde_json::json!(highlight))
+                            }
+                            None => resource,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ToJsonError::to_json_error)?,
+            ),
+    ))
+}
+
+pub async fn api_redirect(ctx: web::Data<Context>) -> impl Responder {
     HttpResponse::SeeOther()
-        .insert_header(("location", "/api"))
+        .insert_header(("location", format!("{}/api", ctx.url_prefix())))
         .finish()
 }
 
+#[actix_web::get("/admin/integrity")]
+pub async fn get_admin_integrity(ctx: web::Data<Context>) -> JsonResponseResult<impl Responder> {
+    if !ctx.admin() {
+        return Err(Error::Forbidden("Admin endpoints are disabled".to_string()).to_json_error());
+    }
+
+    let report = IntegrityReport::check(ctx.library().as_ref())
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    Ok(web::Json(report))
+}
+
+#[actix_web::get("/suggest")]
+pub async fn get_suggest(
+    ctx: web::Data<Context>,
+    suggest: web::Query<Suggest>,
+) -> JsonResponseResult<impl Responder> {
+    let Suggest { q, limit } = suggest.into_inner();
+    let limit = limit.clamp(1, Suggest::MAX_LIMIT);
+
+    let suggestions = Suggestion::find(ctx.library().conn(), &q, limit)
+        .await
+        .map_err(ToJsonError::to_json_error)?;
+
+    Ok(web::Json(
+        Resource::default()
+            .with_link(
+                "self",
+                Link::new(format!(
+                    "/suggest?q={}&limit={limit}",
+                    url::form_urlencoded::byte_serialize(q.as_bytes()).collect::<String>()
+                )),
+            )
+            .with_property("count", suggestions.len())
+            .with_embeddeds(
+                "items",
+                suggestions
+                    .into_iter()
+                    .map(|suggestion| {
+                        let path = match suggestion.kind {
+                            SuggestionKind::Title => "books",
+                            SuggestionKind::Author => "authors",
+                            SuggestionKind::Series => "series",
+                        };
+
+                        Resource::default()
+                            .with_link("self", Link::new(format!("/{path}/{}", suggestion.id)))
+                            .with_property("kind", serde_json::json!(suggestion.kind))
+                            .with_property("id", suggestion.id)
+                            .with_property("value", suggestion.value)
+                            .with_property("books", suggestion.books)
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+    ))
+}
+
 pub fn service() -> actix_web::Scope {
     web::scope("/api")
         .service(get_root)
+        .service(get_book_by_identifier)
+        .service(get_book)
+        .service(get_book_annotations)
+        .service(get_shelves)
+        .service(get_shelf)
+        .service(get_search)
+        .service(get_suggest)
+        .service(get_admin_integrity)
         .service(entity_service::<authors::Entity>("authors"))
         .service(entity_service::<books::Entity>("books"))
+        .service(entity_service::<publishers::Entity>("publishers"))
         .service(entity_service::<series::Entity>("series"))
+        .service(entity_service::<tags::Entity>("tags"))
         .service(web::scope("/0.1.0").default_service(web::to(api_redirect)))
 }
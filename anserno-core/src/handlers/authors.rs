@@ -3,8 +3,8 @@ use std::collections::BTreeMap;
 use actix_web::{web, HttpResponse, Responder};
 use calibre_data::{
     entities::{authors, books_authors_link, flat_books},
-    library::CalibreLibrary,
     pagination::{BucketPaginator, QueryPaginator, RecordsQuery},
+    query::language_filter::LanguageFilter,
 };
 use pagination::paginator::Paginator;
 use sea_orm::{EntityTrait, LoaderTrait, ModelTrait, QueryOrder};
@@ -12,7 +12,7 @@ use sea_orm::{EntityTrait, LoaderTrait, ModelTrait, QueryOrder};
 use crate::{
     context::Context,
     error::{Error, ResponseResult, WithContext},
-    url_params::Pagination,
+    url_params::{Language, Pagination},
 };
 
 #[actix_web::get("")]
@@ -20,7 +20,8 @@ pub async fn get(
     ctx: web::Data<Context>,
     pagination: web::Query<Pagination>,
 ) -> ResponseResult<impl Responder> {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
 
     let Pagination { page, .. } = pagination.into_inner();
 
@@ -76,8 +77,10 @@ pub async fn get_id(
     ctx: web::Data<Context>,
     id: web::Path<i32>,
     pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
 ) -> ResponseResult<impl Responder> {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
 
     let author_id = id.into_inner();
 
@@ -92,9 +95,11 @@ pub async fn get_id(
 
     let Pagination { page, items } = pagination.into_inner();
 
-    let query = author
-        .find_related(flat_books::Entity)
-        .order_by_asc(flat_books::Column::Sort);
+    let query = flat_books::Entity::filter_language_opt(
+        author.find_related(flat_books::Entity),
+        language.lang.as_deref(),
+    )
+    .order_by_asc(flat_books::Column::Sort);
 
     let query_paginator = QueryPaginator::from_query(conn, query)
         .await
@@ -110,7 +115,7 @@ pub async fn get_id(
     let mut tera_context = tera::Context::new();
 
     tera_context.insert("title", &format!("Author - {}", author.name));
-    tera_context.insert("url", &format!("/authors/{}", author.id));
+    tera_context.insert("url", &language.url(format!("/authors/{}", author.id)));
 
     tera_context.insert("flat_books", &flat_books);
 
@@ -1,30 +1,48 @@
 use actix_files::NamedFile;
-use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use actix_web::{body::SizedStream, http::StatusCode, web, HttpRequest, HttpResponse, Responder};
 use calibre_data::{
-    entities::flat_books,
-    library::CalibreLibrary,
+    annotations::{Annotation, ReadingPosition},
+    entities::{flat_books, identifiers},
+    library::{LibraryBackend, ResolvedResource, ResourceRequest},
     pagination::{QueryPaginator, RecordsQuery},
+    query::language_filter::LanguageFilter,
 };
 use pagination::paginator::Paginator;
-use sea_orm::{EntityTrait, QueryOrder};
-use url::Origin;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
 
 use crate::{
     context::Context,
     error::{Error, ResponseResult, WithContext},
-    url_params::Pagination,
+    identifier_links::IdentifierLink,
+    url_params::{BookListing, BookSort, Language, Pagination},
 };
 
 #[actix_web::get("")]
 pub async fn get(
     ctx: web::Data<Context>,
     pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
+    listing: web::Query<BookListing>,
 ) -> ResponseResult<impl Responder> {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
 
     let Pagination { page, items } = pagination.into_inner();
 
-    let query = flat_books::Entity::find().order_by_desc(flat_books::Column::Id);
+    let mut query = flat_books::Entity::filter_language_opt(
+        flat_books::Entity::find(),
+        language.lang.as_deref(),
+    );
+
+    if let Some(min_rating) = listing.min_rating {
+        query = query.filter(flat_books::Column::Rating.gte(min_rating));
+    }
+
+    if let BookSort::Rating = listing.sort {
+        query = query.order_by_desc(flat_books::Column::Rating);
+    }
+
+    let query = query.order_by_desc(flat_books::Column::Id);
 
     let paginator = QueryPaginator::from_query(conn, query)
         .await
@@ -40,7 +58,7 @@ pub async fn get(
     let mut tera_context = tera::Context::new();
 
     tera_context.insert("title", "Books");
-    tera_context.insert("url", "/books");
+    tera_context.insert("url", &listing.url(language.url("/books")));
 
     tera_context.insert("flat_books", &flat_books);
 
@@ -57,10 +75,11 @@ pub async fn get(
 
 async fn find_flat_book(
     ctx: &web::Data<Context>,
+    conn: &DatabaseConnection,
     flat_book_id: i32,
 ) -> ResponseResult<flat_books::Model> {
     flat_books::Entity::find_by_id(flat_book_id)
-        .one(ctx.library().conn())
+        .one(conn)
         .await
         .map_err(|err| err.with_context(ctx))?
         .ok_or(
@@ -71,11 +90,34 @@ async fn find_flat_book(
 
 #[actix_web::get("/{id}")]
 pub async fn get_id(ctx: web::Data<Context>, id: web::Path<i32>) -> ResponseResult<impl Responder> {
-    let flat_book = find_flat_book(&ctx, id.into_inner()).await?;
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let flat_book = find_flat_book(&ctx, conn, id.into_inner()).await?;
+
+    let identifiers = flat_book
+        .identifiers
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(kind, value)| {
+            value.as_str().map(|value| {
+                IdentifierLink::new(ctx.identifier_links(), kind.clone(), value.to_string())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let custom_fields = library
+        .custom_columns()
+        .book_fields(conn, flat_book.id)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
 
     let mut tera_context = tera::Context::new();
 
     tera_context.insert("flat_book", &flat_book);
+    tera_context.insert("identifiers", &identifiers);
+    tera_context.insert("custom_fields", &custom_fields);
 
     ctx.template_engine()
         .render("books/id.html", &tera_context)
@@ -83,38 +125,101 @@ pub async fn get_id(ctx: web::Data<Context>, id: web::Path<i32>) -> ResponseResu
         .map_err(|err| err.with_context(&ctx))
 }
 
+#[derive(serde::Deserialize)]
+pub struct IdentifierRequest {
+    kind: String,
+    value: String,
+}
+
+#[actix_web::get("/by-identifier/{kind}/{value}")]
+pub async fn get_by_identifier(
+    ctx: web::Data<Context>,
+    identifier_request: web::Path<IdentifierRequest>,
+) -> ResponseResult<impl Responder> {
+    let IdentifierRequest { kind, value } = identifier_request.into_inner();
+
+    let identifier = identifiers::Entity::find_by_kind_value(&kind, &value)
+        .one(ctx.library().conn())
+        .await
+        .map_err(|err| err.with_context(&ctx))?
+        .ok_or(
+            Error::NotFound(format!("No record found for Identifier({kind}={value})"))
+                .with_context(&ctx),
+        )?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((
+            "location",
+            format!("{}/books/{}", ctx.url_prefix(), identifier.book),
+        ))
+        .finish())
+}
+
+/// Serve the resource `filename` of `flat_book`, as found in `library`.
 async fn flat_book_file(
     ctx: &web::Data<Context>,
     req: &HttpRequest,
-    flat_book_id: i32,
+    library: &dyn LibraryBackend,
+    flat_book: &flat_books::Model,
     filename: &str,
-) -> ResponseResult<impl Responder> {
-    let flat_book = find_flat_book(ctx, flat_book_id).await?;
-
-    let file_resource = ctx
-        .library()
-        .flat_book_resource_path(&flat_book, filename)
+) -> ResponseResult<HttpResponse> {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    let resource_request = ResourceRequest {
+        range: header("range"),
+        if_range: header("if-range"),
+        if_none_match: header("if-none-match"),
+        if_modified_since: header("if-modified-since"),
+    };
+
+    let file_resource = library
+        .flat_book_resource(flat_book, filename, &resource_request)
+        .await
         .map_err(|err| err.with_context(ctx))?;
 
-    match file_resource.origin() {
-        Origin::Opaque(_) => {
-            let path = file_resource
-                .to_file_path()
-                .map_err(|_| Error::Unknown.with_context(ctx))?;
-
-            NamedFile::open(path)
-                .map(|named_file| {
-                    named_file
-                        .use_etag(true)
-                        .use_last_modified(true)
-                        .into_response(req)
-                })
-                .map_err(|err| err.with_context(ctx))
-        }
-
-        Origin::Tuple(_, _, _) => Ok(HttpResponse::SeeOther()
-            .insert_header(("location", file_resource.as_str()))
+    match file_resource {
+        ResolvedResource::Path(path) => NamedFile::open(path)
+            .map(|named_file| {
+                named_file
+                    .use_etag(true)
+                    .use_last_modified(true)
+                    .into_response(req)
+            })
+            .map_err(|err| err.with_context(ctx)),
+
+        ResolvedResource::Url(url) => Ok(HttpResponse::SeeOther()
+            .insert_header(("location", url.as_str()))
             .finish()),
+
+        ResolvedResource::Stream(stream) => {
+            let mut response = HttpResponse::build(
+                StatusCode::from_u16(stream.status)
+                    .map_err(|_| Error::Unknown.with_context(ctx))?,
+            );
+
+            for (name, value) in [
+                ("content-type", stream.content_type),
+                ("etag", stream.etag),
+                ("last-modified", stream.last_modified),
+                ("content-range", stream.content_range),
+                ("accept-ranges", stream.accept_ranges),
+            ] {
+                if let Some(value) = value {
+                    response.insert_header((name, value));
+                }
+            }
+
+            Ok(match stream.content_length {
+                _ if stream.status == StatusCode::NOT_MODIFIED.as_u16() => response.finish(),
+                Some(length) => response.body(SizedStream::new(length, stream.body)),
+                None => response.streaming(stream.body),
+            })
+        }
     }
 }
 
@@ -124,7 +229,11 @@ pub async fn get_id_cover(
     req: HttpRequest,
     id: web::Path<i32>,
 ) -> ResponseResult<impl Responder> {
-    flat_book_file(&ctx, &req, id.into_inner(), "cover.jpg").await
+    let library = ctx.library();
+
+    let flat_book = find_flat_book(&ctx, library.conn(), id.into_inner()).await?;
+
+    flat_book_file(&ctx, &req, library.as_ref(), &flat_book, "cover.jpg").await
 }
 
 #[actix_web::get("/{id}/thumb")]
@@ -133,7 +242,11 @@ pub async fn get_id_thumb(
     req: HttpRequest,
     id: web::Path<i32>,
 ) -> ResponseResult<impl Responder> {
-    flat_book_file(&ctx, &req, id.into_inner(), "thumb.jpg").await
+    let library = ctx.library();
+
+    let flat_book = find_flat_book(&ctx, library.conn(), id.into_inner()).await?;
+
+    flat_book_file(&ctx, &req, library.as_ref(), &flat_book, "thumb.jpg").await
 }
 
 #[derive(serde::Deserialize)]
@@ -150,7 +263,9 @@ pub async fn get_id_download_format(
 ) -> ResponseResult<impl Responder> {
     let DownloadRequest { id, format } = download_request.into_inner();
 
-    let flat_book = find_flat_book(&ctx, id).await?;
+    let library = ctx.library();
+
+    let flat_book = find_flat_book(&ctx, library.conn(), id).await?;
 
     let formats = flat_book
         .formats
@@ -166,6 +281,7 @@ pub async fn get_id_download_format(
 
     let format_path = formats
         .get(&format.to_uppercase())
+        .and_then(|format| format.get("name"))
         .and_then(serde_json::Value::as_str)
         .map(|filename| format!("{filename}.{}", format.to_lowercase()))
         .ok_or(
@@ -176,7 +292,7 @@ pub async fn get_id_download_format(
             .with_context(&ctx),
         )?;
 
-    flat_book_file(&ctx, &req, flat_book.id, &format_path).await
+    flat_book_file(&ctx, &req, library.as_ref(), &flat_book, &format_path).await
 }
 
 #[actix_web::get("/{id}/read")]
@@ -184,7 +300,7 @@ pub async fn get_id_read(
     ctx: web::Data<Context>,
     id: web::Path<i32>,
 ) -> ResponseResult<impl Responder> {
-    let flat_book = find_flat_book(&ctx, id.into_inner()).await?;
+    let flat_book = find_flat_book(&ctx, ctx.library().conn(), id.into_inner()).await?;
     let mut tera_context = tera::Context::new();
 
     tera_context.insert("flat_book", &flat_book);
@@ -195,12 +311,44 @@ pub async fn get_id_read(
         .map_err(|err| err.with_context(&ctx))
 }
 
+#[actix_web::get("/{id}/annotations")]
+pub async fn get_id_annotations(
+    ctx: web::Data<Context>,
+    id: web::Path<i32>,
+) -> ResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let flat_book = find_flat_book(&ctx, conn, id.into_inner()).await?;
+
+    let annotations = Annotation::find_by_book(conn, flat_book.id)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let reading_positions = ReadingPosition::find_by_book(conn, flat_book.id)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("flat_book", &flat_book);
+    tera_context.insert("annotations", &annotations);
+    tera_context.insert("reading_positions", &reading_positions);
+
+    ctx.template_engine()
+        .render("books/id/annotations.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| err.with_context(&ctx))
+}
+
 pub fn service() -> actix_web::Scope {
     actix_web::Scope::new("/books")
         .service(get)
+        .service(get_by_identifier)
         .service(get_id)
         .service(get_id_cover)
         .service(get_id_thumb)
         .service(get_id_download_format)
         .service(get_id_read)
+        .service(get_id_annotations)
 }
@@ -1,5 +1,5 @@
 use actix_web::{http::header::ContentType, web, HttpResponse, Responder};
-use calibre_data::{entities::flat_books, library::CalibreLibrary};
+use calibre_data::entities::flat_books;
 use sea_orm::{
     sea_query::{Func, SimpleExpr},
     EntityTrait, QueryOrder, QuerySelect,
@@ -11,7 +11,8 @@ use crate::{
     url_params::Pagination,
 };
 
-#[actix_web::get("/")]
+/// Index page, registered by `service` at both `""` and `"/"` so it also
+/// answers at the root of library scopes.
 pub async fn get(
     ctx: web::Data<Context>,
     pagination: web::Query<Pagination>,
@@ -43,6 +44,10 @@ pub async fn get_robots_txt() -> impl Responder {
         .body(["User-agent: *", "Disallow: /"].join("\r\n"))
 }
 
+pub fn service() -> actix_web::Resource {
+    web::resource(["", "/"]).route(web::get().to(get))
+}
+
 pub async fn default_service(ctx: web::Data<Context>) -> ResponseResult<impl Responder> {
     Err::<HttpResponse, ResponseError>(Error::NotFound("".to_string()).with_context(&ctx))
 }
@@ -0,0 +1,23 @@
+use actix_web::{web, HttpResponse, Responder};
+
+use crate::{
+    context::Context,
+    error::{ResponseResult, WithContext},
+};
+
+#[actix_web::get("")]
+pub async fn get(ctx: web::Data<Context>) -> ResponseResult<impl Responder> {
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("title", "Libraries");
+    tera_context.insert("libraries", ctx.libraries());
+
+    ctx.template_engine()
+        .render("libraries.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| err.with_context(&ctx))
+}
+
+pub fn service() -> actix_web::Scope {
+    actix_web::Scope::new("/libraries").service(get)
+}
@@ -2,6 +2,10 @@ pub mod api;
 pub mod authors;
 pub mod books;
 pub mod index;
+pub mod libraries;
+pub mod publishers;
 pub mod search;
 pub mod series;
+pub mod shelves;
 pub mod static_files;
+pub mod tags;
@@ -1,36 +1,121 @@
+use std::collections::BTreeMap;
+
 use actix_web::{web, HttpResponse, Responder};
 use calibre_data::{
+    custom_columns::CustomColumns,
     entities::{flat_books, search_index},
-    library::CalibreLibrary,
     pagination::{QueryPaginator, RecordsQuery},
-    query::select_alias::SelectAlias,
+    query::{language_filter::LanguageFilter, select_alias::SelectAlias},
+    search::{Expression, FullTextQuery, SearchHighlight, SearchWeights},
+    shelves::Shelves,
 };
 use pagination::paginator::Paginator;
-use sea_orm::{prelude::Expr, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use sea_orm::{
+    prelude::Expr, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+};
 
 use crate::{
     context::Context,
-    error::{ResponseResult, WithContext},
+    error::{Error, ResponseResult, WithContext},
     url_params,
 };
 
+/// Search index entries of the books matching `search`, along with the full
+/// text query it was compiled to, if any. Calibre search expressions resolve
+/// `#label` fields in `custom_columns`.
+///
+/// Full text hits are ranked by relevance using `weights`, and Calibre search
+/// expression hits are in sort order. Searches which fail to parse, or refer
+/// to saved searches which cannot be read, are reported as bad requests.
+pub async fn search_query(
+    conn: &DatabaseConnection,
+    custom_columns: &CustomColumns,
+    search: &url_params::Search,
+    weights: &SearchWeights,
+) -> Result<(Select<search_index::Entity>, Option<FullTextQuery>), Error> {
+    match (&search.calibre, &search.query) {
+        (Some(calibre), _) => {
+            let expression = calibre
+                .parse::<Expression>()
+                .map_err(|err| Error::BadRequest(err.to_string()))?;
+
+            // Saved searches are only read when referenced, so a broken
+            // preference only fails the searches using it.
+            let saved_searches = match expression.references_saved_searches() {
+                true => Shelves::load_saved_searches(conn)
+                    .await
+                    .map_err(|err| match err {
+                        calibre_data::error::Error::Search(_) => Error::BadRequest(err.to_string()),
+                        err => err.into(),
+                    })?,
+                false => BTreeMap::new(),
+            };
+
+            let condition = expression
+                .condition(
+                    search_index::Column::BookId,
+                    &saved_searches,
+                    custom_columns,
+                )
+                .map_err(|err| Error::BadRequest(err.to_string()))?;
+
+            Ok((
+                search_index::Entity::find()
+                    .filter(condition)
+                    .order_by_asc(search_index::Column::Sort),
+                None,
+            ))
+        }
+
+        (None, Some(query)) => {
+            let full_text_query = query
+                .parse::<FullTextQuery>()
+                .map_err(|err| Error::BadRequest(err.to_string()))?;
+
+            Ok((
+                search_index::Entity::find()
+                    .filter(
+                        Expr::col(SelectAlias("anserno_search_index")).eq(full_text_query.as_str()),
+                    )
+                    .order_by_asc(weights.rank())
+                    .order_by_asc(search_index::Column::Sort),
+                Some(full_text_query),
+            ))
+        }
+
+        (None, None) => Err(Error::BadRequest(
+            "Missing search, set either query or calibre".to_string(),
+        )),
+    }
+}
+
 #[actix_web::get("")]
 pub async fn get(
     ctx: web::Data<Context>,
-    query: web::Query<url_params::Search>,
+    search: web::Query<url_params::Search>,
     pagination: web::Query<url_params::Pagination>,
+    language: web::Query<url_params::Language>,
 ) -> ResponseResult<impl Responder> {
-    let conn = ctx.library().conn();
+    let library = ctx.library();
+    let conn = library.conn();
 
-    let query = &query.into_inner().query;
+    let search = search.into_inner();
 
     let url_params::Pagination { page, items } = pagination.into_inner();
 
-    let search_query = search_index::Entity::find()
-        .filter(Expr::col(SelectAlias("anserno_search_index")).eq(query))
-        .select_only()
-        .column(search_index::Column::BookId)
-        .order_by_asc(search_index::Column::Sort);
+    let (search_query, full_text_query) = search_query(
+        conn,
+        library.custom_columns(),
+        &search,
+        ctx.search_weights(),
+    )
+    .await
+    .map_err(|err| err.with_context(&ctx))?;
+
+    let search_query =
+        search_index::Entity::filter_language_opt(search_query, language.lang.as_deref())
+            .select_only()
+            .column(search_index::Column::BookId);
 
     let paginator = QueryPaginator::from_query(conn, search_query)
         .await
@@ -50,12 +135,34 @@ pub async fn get(
         .flat_map(|(_, books)| books)
         .collect::<Vec<_>>();
 
+    let highlights = match &full_text_query {
+        Some(full_text_query) => SearchHighlight::find(
+            conn,
+            full_text_query,
+            &flat_books
+                .iter()
+                .map(|flat_book| flat_book.id)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .map_err(|err| err.with_context(&ctx))?,
+        None => Default::default(),
+    };
+
     let mut tera_context = tera::Context::new();
 
     tera_context.insert("title", "Search Results");
-    tera_context.insert("url", &format!("/search?query={}", query));
+    tera_context.insert("url", &language.url(search.url("/search")));
 
     tera_context.insert("flat_books", &flat_books);
+    // Keyed by string, as templates index maps with strings.
+    tera_context.insert(
+        "highlights",
+        &highlights
+            .into_iter()
+            .map(|(book_id, highlight)| (book_id.to_string(), highlight))
+            .collect::<BTreeMap<_, _>>(),
+    );
 
     tera_context.insert("paginator", &paginator);
     tera_context.insert("paginator_series", &paginator.series(page));
@@ -71,3 +178,45 @@ pub async fn get(
 pub fn service() -> actix_web::Scope {
     actix_web::Scope::new("/search").service(get)
 }
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App};
+
+    use crate::{config, testing};
+
+    #[actix_web::test]
+    async fn broken_saved_searches_only_fail_searches_using_them() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let ctx = testing::context(
+            directory.path(),
+            "UPDATE preferences SET val = '{not json' WHERE key = 'saved_searches';",
+        )
+        .await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx))
+                .configure(config::configure_library),
+        )
+        .await;
+
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        for uri in [
+            "/search?calibre=tags:Classic",
+            "/api/search?calibre=tags:Classic",
+        ] {
+            let response = test::call_service(&app, get(uri)).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+
+        for uri in [
+            "/search?calibre=search:Unread",
+            "/api/search?calibre=search:Unread",
+        ] {
+            let response = test::call_service(&app, get(uri)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+    }
+}
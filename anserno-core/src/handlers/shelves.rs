@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse, Responder};
+use calibre_data::{
+    entities::flat_books,
+    pagination::{QueryPaginator, RecordsQuery},
+    query::language_filter::LanguageFilter,
+    shelves::Shelves,
+};
+use pagination::paginator::Paginator;
+use sea_orm::{EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+use crate::{
+    context::Context,
+    error::{Error, ResponseResult, WithContext},
+    url_params::{Language, Pagination},
+};
+
+/// Number of books previewed for each shelf on the shelves page.
+const SHELF_PREVIEW_LENGTH: u64 = 12;
+
+/// Encode a shelf name for use as a url path segment.
+pub fn shelf_id(name: &str) -> String {
+    url::form_urlencoded::byte_serialize(name.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+#[derive(serde::Serialize)]
+struct ShelfContainer<'a> {
+    id: String,
+    name: &'a str,
+}
+
+#[actix_web::get("")]
+pub async fn get(ctx: web::Data<Context>) -> ResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let shelves = Shelves::load(conn)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let mut container = Vec::new();
+    let mut shelves_flat_books = BTreeMap::new();
+
+    for shelf in shelves.iter() {
+        // Searches using syntax we cannot translate are left off the page
+        // rather than failing it, the shelf page itself reports the error as
+        // a bad request.
+        let Ok(condition) =
+            shelves.condition(shelf, flat_books::Column::Id, library.custom_columns())
+        else {
+            continue;
+        };
+
+        let flat_books = flat_books::Entity::find()
+            .filter(condition)
+            .order_by_asc(flat_books::Column::Sort)
+            .limit(SHELF_PREVIEW_LENGTH)
+            .all(conn)
+            .await
+            .map_err(|err| err.with_context(&ctx))?;
+
+        let id = shelf_id(&shelf.name);
+
+        shelves_flat_books.insert(id.clone(), flat_books);
+        container.push(ShelfContainer {
+            id,
+            name: &shelf.name,
+        });
+    }
+
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("title", "Shelves");
+    tera_context.insert("url", "/shelves");
+
+    tera_context.insert("container", &container);
+    tera_context.insert("flat_books_map", &shelves_flat_books);
+
+    ctx.template_engine()
+        .render("container.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| Error::from(err).with_context(&ctx))
+}
+
+#[actix_web::get("/{name}")]
+pub async fn get_name(
+    ctx: web::Data<Context>,
+    name: web::Path<String>,
+    pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
+) -> ResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let name = name.into_inner();
+
+    let shelves = Shelves::load(conn)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let shelf = shelves.get(&name).ok_or(
+        Error::NotFound(format!("No record found for Shelf(name={name})")).with_context(&ctx),
+    )?;
+
+    let condition = shelves
+        .condition(shelf, flat_books::Column::Id, library.custom_columns())
+        .map_err(|err| Error::BadRequest(err.to_string()).with_context(&ctx))?;
+
+    let Pagination { page, items } = pagination.into_inner();
+
+    let query = flat_books::Entity::filter_language_opt(
+        flat_books::Entity::find().filter(condition),
+        language.lang.as_deref(),
+    )
+    .order_by_asc(flat_books::Column::Sort);
+
+    let query_paginator = QueryPaginator::from_query(conn, query)
+        .await
+        .map_err(|err| err.with_context(&ctx))?
+        .with_page_length(items);
+
+    let flat_books = query_paginator
+        .records_query(page)
+        .all(conn)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("title", &format!("Shelf - {}", shelf.name));
+    tera_context.insert(
+        "url",
+        &language.url(format!("/shelves/{}", shelf_id(&shelf.name))),
+    );
+
+    tera_context.insert("flat_books", &flat_books);
+
+    tera_context.insert("paginator", &query_paginator);
+    tera_context.insert("paginator_series", &query_paginator.series(page));
+    tera_context.insert("paginator_page", &query_paginator.page(page));
+    tera_context.insert("paginator_items", &items);
+
+    ctx.template_engine()
+        .render("list.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| err.with_context(&ctx))
+}
+
+pub fn service() -> actix_web::Scope {
+    actix_web::Scope::new("/shelves")
+        .service(get)
+        .service(get_name)
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App};
+
+    use crate::{config, testing};
+
+    #[actix_web::test]
+    async fn untranslatable_shelf_is_a_bad_request() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let ctx = testing::context(
+            directory.path(),
+            r#"UPDATE preferences
+               SET val = '{"Classics": "tags:Classic", "Broken": "nosuchfield:x"}'
+               WHERE key = 'virtual_libraries';"#,
+        )
+        .await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ctx))
+                .configure(config::configure_library),
+        )
+        .await;
+
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        let response = test::call_service(&app, get("/shelves/Broken")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(response).await;
+        assert!(String::from_utf8_lossy(&body).contains("nosuchfield"));
+
+        let response = test::call_service(&app, get("/api/shelves/Broken")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = test::call_service(&app, get("/shelves/Classics")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = test::call_service(&app, get("/shelves")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
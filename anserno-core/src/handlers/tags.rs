@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse, Responder};
+use calibre_data::{
+    entities::{books_tags_link, flat_books, tags},
+    pagination::{BucketPaginator, QueryPaginator, RecordsQuery},
+    query::language_filter::LanguageFilter,
+};
+use pagination::paginator::Paginator;
+use sea_orm::{EntityTrait, LoaderTrait, ModelTrait, QueryOrder};
+
+use crate::{
+    context::Context,
+    error::{Error, ResponseResult, WithContext},
+    url_params::{Language, Pagination},
+};
+
+#[actix_web::get("")]
+pub async fn get(
+    ctx: web::Data<Context>,
+    pagination: web::Query<Pagination>,
+) -> ResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let Pagination { page, .. } = pagination.into_inner();
+
+    let bucket_paginator =
+        BucketPaginator::from_query(conn, tags::Entity::find(), tags::Column::Name, 1)
+            .await
+            .map_err(|err| Error::from(err).with_context(&ctx))?;
+
+    let tags = bucket_paginator
+        .records_query(page)
+        .all(conn)
+        .await
+        .map_err(|err| Error::from(err).with_context(&ctx))?;
+
+    let mut flat_books = tags
+        .load_many_to_many(flat_books::Entity, books_tags_link::Entity, conn)
+        .await
+        .map_err(|err| Error::from(err).with_context(&ctx))?;
+
+    for books in flat_books.iter_mut() {
+        books.sort_by(|left, right| left.sort.cmp(&right.sort));
+    }
+
+    let tags_flat_books: BTreeMap<i32, Vec<flat_books::Model>> =
+        tags.iter().map(|tag| tag.id).zip(flat_books).collect();
+
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("title", "Tags");
+    tera_context.insert("url", "/tags");
+    tera_context.insert("include_jump", &true);
+
+    tera_context.insert("container", &tags);
+
+    tera_context.insert("paginator", &bucket_paginator);
+    tera_context.insert("paginator_series", &bucket_paginator.series(page));
+    tera_context.insert("paginator_page", &bucket_paginator.page(page));
+    tera_context.insert("paginator_items", &0);
+
+    tera_context.insert("flat_books_map", &tags_flat_books);
+
+    ctx.template_engine()
+        .render("container.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| Error::from(err).with_context(&ctx))
+}
+
+#[actix_web::get("/{id}")]
+pub async fn get_id(
+    ctx: web::Data<Context>,
+    id: web::Path<i32>,
+    pagination: web::Query<Pagination>,
+    language: web::Query<Language>,
+) -> ResponseResult<impl Responder> {
+    let library = ctx.library();
+    let conn = library.conn();
+
+    let tag_id = id.into_inner();
+
+    let tag = tags::Entity::find_by_id(tag_id)
+        .one(conn)
+        .await
+        .map_err(|err| err.with_context(&ctx))?
+        .ok_or(
+            Error::NotFound(format!("No record found for Tag(id={tag_id})")).with_context(&ctx),
+        )?;
+
+    let Pagination { page, items } = pagination.into_inner();
+
+    let query = flat_books::Entity::filter_language_opt(
+        tag.find_related(flat_books::Entity),
+        language.lang.as_deref(),
+    )
+    .order_by_asc(flat_books::Column::Sort);
+
+    let query_paginator = QueryPaginator::from_query(conn, query)
+        .await
+        .map_err(|err| err.with_context(&ctx))?
+        .with_page_length(items);
+
+    let flat_books = query_paginator
+        .records_query(page)
+        .all(conn)
+        .await
+        .map_err(|err| err.with_context(&ctx))?;
+
+    let mut tera_context = tera::Context::new();
+
+    tera_context.insert("title", &format!("Tag - {}", tag.name));
+    tera_context.insert("url", &language.url(format!("/tags/{}", tag.id)));
+
+    tera_context.insert("flat_books", &flat_books);
+
+    tera_context.insert("paginator", &query_paginator);
+    tera_context.insert("paginator_series", &query_paginator.series(page));
+    tera_context.insert("paginator_page", &query_paginator.page(page));
+    tera_context.insert("paginator_items", &items);
+
+    ctx.template_engine()
+        .render("list.html", &tera_context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(|err| err.with_context(&ctx))
+}
+
+pub fn service() -> actix_web::Scope {
+    actix_web::Scope::new("/tags").service(get).service(get_id)
+}
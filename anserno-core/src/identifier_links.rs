@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+/// Characters escaped in identifier values, everything but the unreserved
+/// `A-Za-z0-9-._~`.
+const VALUE_ESCAPED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Outbound link templates for Calibre book identifiers, keyed by identifier
+/// type. The `{value}` placeholder is replaced by the percent-encoded
+/// identifier value, or by the value as is when it makes up the whole url.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub struct IdentifierLinks(BTreeMap<String, String>);
+
+impl IdentifierLinks {
+    /// Add or replace the template for an identifier type.
+    pub fn with_template(self, kind: impl AsRef<str>, template: impl Into<String>) -> Self {
+        let mut identifier_links = self;
+        identifier_links
+            .0
+            .insert(kind.as_ref().to_lowercase(), template.into());
+        identifier_links
+    }
+
+    /// Render the outbound link for an identifier, if a template exists and
+    /// the result is a valid http(s) url.
+    pub fn link(&self, kind: &str, value: &str) -> Option<url::Url> {
+        self.0
+            .get(&kind.to_lowercase())
+            .map(|template| match template.as_str() {
+                "{value}" => value.to_string(),
+                template => template.replace(
+                    "{value}",
+                    &percent_encoding::utf8_percent_encode(value, VALUE_ESCAPED).to_string(),
+                ),
+            })
+            .and_then(|link| url::Url::parse(&link).ok())
+            .filter(|link| matches!(link.scheme(), "http" | "https"))
+    }
+}
+
+impl ::std::default::Default for IdentifierLinks {
+    fn default() -> Self {
+        Self(BTreeMap::from(
+            [
+                ("amazon", "https://www.amazon.com/dp/{value}"),
+                ("doi", "https://doi.org/{value}"),
+                ("goodreads", "https://www.goodreads.com/book/show/{value}"),
+                ("google", "https://books.google.com/books?id={value}"),
+                ("isbn", "https://www.worldcat.org/isbn/{value}"),
+                ("issn", "https://www.worldcat.org/issn/{value}"),
+                ("openlibrary", "https://openlibrary.org/works/{value}"),
+                ("uri", "{value}"),
+                ("url", "{value}"),
+            ]
+            .map(|(kind, template)| (kind.to_string(), template.to_string())),
+        ))
+    }
+}
+
+#[derive(::std::fmt::Debug, serde::Serialize)]
+pub struct IdentifierLink {
+    pub kind: String,
+    pub value: String,
+    pub url: Option<String>,
+}
+
+impl IdentifierLink {
+    pub fn new(identifier_links: &IdentifierLinks, kind: String, value: String) -> Self {
+        let url = identifier_links
+            .link(&kind, &value)
+            .map(|url| url.to_string());
+
+        Self { kind, value, url }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn link_escapes_values() {
+        let identifier_links = IdentifierLinks::default();
+
+        assert_eq!(
+            identifier_links
+                .link("google", "a b&c=d#e?f/g")
+                .unwrap()
+                .as_str(),
+            "https://books.google.com/books?id=a%20b%26c%3Dd%23e%3Ff%2Fg"
+        );
+        assert_eq!(
+            identifier_links
+                .link("isbn", "9780547773742")
+                .unwrap()
+                .as_str(),
+            "https://www.worldcat.org/isbn/9780547773742"
+        );
+        assert_eq!(
+            identifier_links
+                .link("url", "https://example.com/book?id=1&lang=en")
+                .unwrap()
+                .as_str(),
+            "https://example.com/book?id=1&lang=en"
+        );
+        assert_eq!(identifier_links.link("url", "javascript:alert(1)"), None);
+    }
+}
@@ -2,4 +2,11 @@ pub mod config;
 pub mod context;
 pub mod error;
 pub mod handlers;
+pub mod identifier_links;
+pub mod libraries;
+pub mod refresh;
+pub mod shared_library;
 pub mod url_params;
+
+#[cfg(test)]
+mod testing;
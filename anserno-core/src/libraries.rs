@@ -0,0 +1,78 @@
+use std::{collections::HashMap, sync::Arc};
+
+/// Path prefix under which every library is mounted, `/l/{name}`.
+pub const LIBRARY_SCOPE: &str = "/l";
+
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct LibraryEntry {
+    pub name: String,
+    pub url_prefix: String,
+    pub default: bool,
+}
+
+/// Registry of the libraries served by the process. The default library is
+/// additionally served at the root paths.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::default::Default, serde::Serialize)]
+pub struct Libraries(Arc<Vec<LibraryEntry>>);
+
+impl Libraries {
+    /// Build the registry from library names, in display order.
+    pub fn new<I, S>(names: I, default: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self(Arc::new(
+            names
+                .into_iter()
+                .map(Into::into)
+                .map(|name| LibraryEntry {
+                    url_prefix: Self::url_prefix(&name),
+                    default: name == default,
+                    name,
+                })
+                .collect(),
+        ))
+    }
+
+    /// Url prefix of the scoped routes of the library `name`.
+    pub fn url_prefix(name: &str) -> String {
+        format!("{LIBRARY_SCOPE}/{name}")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LibraryEntry> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn default_library(&self) -> Option<&LibraryEntry> {
+        self.0.iter().find(|library| library.default)
+    }
+
+    /// Copy of `template_engine` whose templates render links under
+    /// `url_prefix`, through the `library_prefix()` and `libraries()`
+    /// template functions.
+    pub fn template_engine(&self, template_engine: &tera::Tera, url_prefix: &str) -> tera::Tera {
+        let mut template_engine = template_engine.clone();
+
+        let url_prefix = tera::Value::String(url_prefix.to_string());
+        template_engine
+            .register_function("library_prefix", move |_: &HashMap<String, tera::Value>| {
+                Ok(url_prefix.clone())
+            });
+
+        let libraries = tera::to_value(self).unwrap_or_default();
+        template_engine.register_function("libraries", move |_: &HashMap<String, tera::Value>| {
+            Ok(libraries.clone())
+        });
+
+        template_engine
+    }
+}
@@ -0,0 +1,172 @@
+use std::{path::Path, time::Duration};
+
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+
+use crate::shared_library::SharedLibrary;
+
+/// Quiet period after the last change of a watched database before it is
+/// reopened, so a write in progress is not picked up halfway.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Keep `library` up to date with its source, swapping in a freshly fetched
+/// library whenever it changed. The new library is fully connected, with its
+/// flat books view and search index rebuilt, before the swap. Failed
+/// refreshes are logged and retried at the next change while the current
+/// library keeps serving.
+///
+/// Databases on the local filesystem are watched for changes, falling back
+/// to polling every `interval` when they cannot be, as are remote sources.
+pub async fn watch<C>(library: SharedLibrary, name: String, interval: Duration, configurer: C)
+where
+    C: Fn(&mut sea_orm::ConnectOptions) + ::core::marker::Send + ::core::marker::Sync + 'static,
+{
+    if let Some(database) = library.load().local_database() {
+        match watch_database(&library, &name, &database, &configurer).await {
+            Ok(()) => return,
+            Err(err) => tracing::warn!(
+                "Failed watching {} of library {name}, polling it instead: {err}",
+                database.display()
+            ),
+        }
+    }
+
+    poll(&library, &name, interval, &configurer).await
+}
+
+/// Refresh `library` whenever the directory of `database` reports a change
+/// to it, once changes settled. Returns when the watch stops.
+async fn watch_database<C>(
+    library: &SharedLibrary,
+    name: &str,
+    database: &Path,
+    configurer: &C,
+) -> notify_debouncer_mini::notify::Result<()>
+where
+    C: Fn(&mut sea_orm::ConnectOptions) + ::core::marker::Send + ::core::marker::Sync + 'static,
+{
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut debouncer = new_debouncer(WATCH_DEBOUNCE, move |result: DebounceEventResult| {
+        let _ = sender.send(result);
+    })?;
+
+    // Watching the directory rather than the file itself keeps track of
+    // databases replaced by a rename, and of their journal files.
+    let directory = database.parent().unwrap_or(Path::new("."));
+    debouncer
+        .watcher()
+        .watch(directory, RecursiveMode::NonRecursive)?;
+
+    tracing::info!("Watching {} for changes", database.display());
+
+    let file_name = database.file_name().unwrap_or_default().to_string_lossy();
+
+    while let Some(result) = receiver.recv().await {
+        match result {
+            Ok(events) => {
+                if events.iter().any(|event| {
+                    event
+                        .path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with(&*file_name))
+                }) {
+                    refresh(library, name, configurer).await;
+                }
+            }
+            Err(err) => tracing::warn!("Failed watching library {name}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the source of `library` for changes every `interval`.
+async fn poll<C>(library: &SharedLibrary, name: &str, interval: Duration, configurer: &C)
+where
+    C: Fn(&mut sea_orm::ConnectOptions) + ::core::marker::Send + ::core::marker::Sync + 'static,
+{
+    loop {
+        actix_web::rt::time::sleep(interval).await;
+
+        refresh(library, name, configurer).await;
+    }
+}
+
+/// Swap in a freshly connected library when the source of `library` changed.
+async fn refresh<C>(library: &SharedLibrary, name: &str, configurer: &C)
+where
+    C: Fn(&mut sea_orm::ConnectOptions) + ::core::marker::Send + ::core::marker::Sync + 'static,
+{
+    let current = library.load();
+
+    match current.is_stale().await {
+        Ok(false) => return,
+        Ok(true) => {}
+        Err(err) => {
+            tracing::warn!("Failed checking library {name} for changes: {err}");
+            return;
+        }
+    }
+
+    tracing::info!("Refreshing library {name} from {}", current.source());
+
+    match current.refreshed(configurer).await {
+        Ok(Some(refreshed)) => {
+            library.store(refreshed);
+            tracing::info!("Refreshed library {name}");
+        }
+        Ok(None) => tracing::info!("Library {name} is unchanged"),
+        Err(err) => tracing::warn!("Failed refreshing library {name}: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Instant};
+
+    use calibre_data::entities::books;
+    use sea_orm::EntityTrait;
+
+    use super::*;
+    use crate::testing;
+
+    #[actix_web::test]
+    async fn swaps_library_when_local_database_changes() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let library = SharedLibrary::new(testing::library(directory.path(), "").await);
+        let original = library.load();
+
+        // Polling alone would not notice the change within the test.
+        actix_web::rt::spawn(watch(
+            library.clone(),
+            "test".to_string(),
+            Duration::from_secs(3600),
+            |_: &mut sea_orm::ConnectOptions| {},
+        ));
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+
+        calibre_data::testing::execute_sql(
+            &directory.path().join("metadata.db"),
+            "UPDATE books SET title = 'A Wizard of Earthsea, Revised' WHERE id = 1;",
+        )
+        .await
+        .unwrap();
+
+        let started = Instant::now();
+        while Arc::ptr_eq(&library.load(), &original) {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "library was not refreshed"
+            );
+            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let book = books::Entity::find_by_id(1)
+            .one(library.load().conn())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(book.title, "A Wizard of Earthsea, Revised");
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::{Arc, RwLock};
+
+use calibre_data::library::LibraryBackend;
+
+/// Library shared between the contexts serving it, which can be replaced
+/// while requests are in flight. Readers take a snapshot with `load` and
+/// keep using it until they drop it, so a swap never pulls the database
+/// from under a running request.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub struct SharedLibrary(Arc<RwLock<Arc<dyn LibraryBackend>>>);
+
+impl SharedLibrary {
+    pub fn new(library: impl LibraryBackend + 'static) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(library))))
+    }
+
+    /// Snapshot of the current library.
+    pub fn load(&self) -> Arc<dyn LibraryBackend> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Replace the current library, returning the previous one.
+    pub fn store(&self, library: Arc<dyn LibraryBackend>) -> Arc<dyn LibraryBackend> {
+        let mut current = self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        ::std::mem::replace(&mut *current, library)
+    }
+}
+
+impl<L> From<L> for SharedLibrary
+where
+    L: LibraryBackend + 'static,
+{
+    fn from(value: L) -> Self {
+        Self::new(value)
+    }
+}
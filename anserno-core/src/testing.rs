@@ -0,0 +1,34 @@
+//! Fixtures for handler tests.
+
+use std::path::Path;
+
+use calibre_data::library::{CalibreLibrary, LocalLibrary};
+
+use crate::context::Context;
+
+/// Connected local library in `directory`, built from the calibre-data
+/// fixture database followed by `sql`.
+pub async fn library(directory: &Path, sql: &str) -> LocalLibrary {
+    let database = calibre_data::testing::create_library(directory)
+        .await
+        .unwrap();
+    calibre_data::testing::execute_sql(&database, sql)
+        .await
+        .unwrap();
+
+    let mut library = LocalLibrary::new(directory).unwrap();
+    library.connect().await.unwrap();
+    library
+}
+
+/// Context serving the library in `directory`, see `library`.
+pub async fn context(directory: &Path, sql: &str) -> Context {
+    let template_engine =
+        tera::Tera::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*")).unwrap();
+
+    Context::new(
+        library(directory, sql).await,
+        template_engine,
+        concat!(env!("CARGO_MANIFEST_DIR"), "/static"),
+    )
+}
@@ -11,7 +11,133 @@ impl ::std::default::Default for Pagination {
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::default::Default,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum BookSort {
+    #[default]
+    Recent,
+    Rating,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ::std::default::Default)]
+#[serde(default)]
+pub struct BookListing {
+    pub sort: BookSort,
+    pub min_rating: Option<i32>,
+}
+
+impl BookListing {
+    /// Append the listing parameters, if any, to a listing url.
+    pub fn url(&self, url: impl Into<String>) -> String {
+        let mut url = url.into();
+
+        if let BookSort::Rating = self.sort {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!("{url}{separator}sort=rating");
+        }
+
+        if let Some(min_rating) = self.min_rating {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!("{url}{separator}min_rating={min_rating}");
+        }
+
+        url
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ::std::default::Default)]
+#[serde(default)]
 pub struct Search {
-    pub query: String,
+    /// Full text search query
+    pub query: Option<String>,
+    /// Calibre search expression, e.g. `authors:"=Tolkien" and rating:>=4`
+    pub calibre: Option<String>,
+}
+
+impl Search {
+    /// Url of this search under `path`, a Calibre expression taking
+    /// precedence over a full text query.
+    pub fn url(&self, path: &str) -> String {
+        let (name, value) = match (&self.calibre, &self.query) {
+            (Some(calibre), _) => ("calibre", calibre),
+            (None, Some(query)) => ("query", query),
+            (None, None) => return path.to_string(),
+        };
+
+        format!(
+            "{path}?{name}={}",
+            url::form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>()
+        )
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, ::std::default::Default)]
+#[serde(default)]
+pub struct Language {
+    pub lang: Option<String>,
+}
+
+impl Language {
+    /// Append the language parameter, if any, to a listing url.
+    pub fn url(&self, url: impl Into<String>) -> String {
+        let url = url.into();
+
+        let Some(lang) = &self.lang else {
+            return url;
+        };
+
+        let separator = if url.contains('?') { '&' } else { '?' };
+
+        format!(
+            "{url}{separator}lang={}",
+            url::form_urlencoded::byte_serialize(lang.as_bytes()).collect::<String>()
+        )
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Suggest {
+    /// What was typed so far in the search box
+    pub q: String,
+    pub limit: u64,
+}
+
+impl Suggest {
+    /// Most suggestions returned at once.
+    pub const MAX_LIMIT: u64 = 50;
+}
+
+impl ::std::default::Default for Suggest {
+    fn default() -> Self {
+        Suggest {
+            q: String::new(),
+            limit: 10,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn language_url_escapes_lang() {
+        let language = Language {
+            lang: Some("en&page=9".to_string()),
+        };
+
+        assert_eq!(language.url("/books"), "/books?lang=en%26page%3D9");
+        assert_eq!(
+            language.url("/books?page=2"),
+            "/books?page=2&lang=en%26page%3D9"
+        );
+        assert_eq!(Language::default().url("/books"), "/books");
+    }
 }
@@ -0,0 +1,142 @@
+"use strict";
+
+class SearchSuggestions {
+  constructor(input, form) {
+    this.input = input;
+    this.prefix = new URL(form.action).pathname.replace(/\/search$/, "");
+
+    this.list = document.createElement("ul");
+    this.list.className = "search-suggestions";
+    this.list.hidden = true;
+
+    this.timeout = null;
+    this.controller = null;
+    this.selected = -1;
+  }
+
+  get items() {
+    return Array.from(this.list.children);
+  }
+
+  schedule() {
+    clearTimeout(this.timeout);
+    this.timeout = setTimeout(this.fetch.bind(this), 150);
+  }
+
+  async fetch() {
+    this.controller?.abort();
+
+    let value = this.input.value;
+    if (value.trim() === "") {
+      this.close();
+      return;
+    }
+
+    this.controller = new AbortController();
+
+    try {
+      let response = await fetch(
+        `${this.prefix}/api/suggest?q=${encodeURIComponent(value)}`,
+        {signal: this.controller.signal},
+      );
+      if (!response.ok) {
+        this.close();
+        return;
+      }
+
+      let items = (await response.json())?._embedded?.items ?? [];
+      this.render(Array.isArray(items) ? items : [items]);
+    } catch (error) {
+      if (error.name !== "AbortError") {
+        this.close();
+      }
+    }
+  }
+
+  render(items) {
+    this.list.replaceChildren(...items.map((item) => {
+      let link = document.createElement("a");
+      link.href = this.prefix + item._links.self.href;
+      link.textContent = item.value;
+
+      let kind = document.createElement("span");
+      kind.className = "search-suggestion-kind";
+      kind.textContent = item.kind;
+      link.append(kind);
+
+      let entry = document.createElement("li");
+      entry.append(link);
+      return entry;
+    }));
+
+    this.selected = -1;
+    this.list.hidden = items.length === 0;
+  }
+
+  select(index) {
+    let items = this.items;
+    if (items.length === 0) {
+      return;
+    }
+
+    this.selected = (index + items.length) % items.length;
+    items.forEach((item, position) => {
+      item.classList.toggle("selected", position === this.selected);
+    });
+  }
+
+  close() {
+    clearTimeout(this.timeout);
+    this.controller?.abort();
+    this.list.hidden = true;
+    this.selected = -1;
+  }
+
+  keydown(event) {
+    if (this.list.hidden) {
+      return;
+    }
+
+    switch (event.key) {
+    case "ArrowDown":
+      this.select(this.selected + 1);
+      break;
+    case "ArrowUp":
+      this.select(this.selected - 1);
+      break;
+    case "Enter":
+      if (this.selected < 0) {
+        this.close();
+        return;
+      }
+      window.location.assign(this.items[this.selected].querySelector("a").href);
+      break;
+    case "Escape":
+      this.close();
+      break;
+    default:
+      return;
+    }
+
+    event.preventDefault();
+  }
+
+  attach() {
+    this.input.after(this.list);
+    this.input.addEventListener("input", this.schedule.bind(this));
+    this.input.addEventListener("keydown", this.keydown.bind(this));
+    this.input.addEventListener("blur", () => {
+      // Leave a click on a suggestion time to follow its link.
+      setTimeout(this.close.bind(this), 200);
+    });
+  }
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  let form = document.getElementById("search-form");
+  let input = document.querySelector('input[form="search-form"]');
+
+  if (form && input) {
+    new SearchSuggestions(input, form).attach();
+  }
+});
@@ -1,8 +1,15 @@
+use calibre_data::library::{
+    Authorization, Credentials, ResourceCacheOptions, S3Credentials, S3Options, S3Serving,
+};
+
 use crate::logging;
 
 #[derive(clap::Parser)]
 #[command(author, version)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Set log level
     #[clap(long, value_enum, default_value_t = logging::LogLevel::Info, env("ANSERNO_LOG_LEVEL"))]
     pub log_level: logging::LogLevel,
@@ -23,9 +30,159 @@ pub struct Args {
     #[clap(long, default_value_t = 8080, env("ANSERNO_PORT"))]
     pub port: i16,
 
-    /// Source url for Calibre library (can be a file:// url for local library)
-    #[clap(long, env("ANSERNO_LIBRARY_URL"))]
-    pub library_url: url::Url,
+    /// Source url for Calibre library (can be a file:// url for local library),
+    /// served as the library named `default`
+    #[clap(long, env("ANSERNO_LIBRARY_URL"), required_unless_present("libraries"))]
+    pub library_url: Option<url::Url>,
+
+    /// Named Calibre library, as `name=url`, served under `/l/{name}`
+    #[clap(
+        long = "library",
+        value_parser = parse_library,
+        value_delimiter = ',',
+        env("ANSERNO_LIBRARIES")
+    )]
+    pub libraries: Vec<(String, url::Url)>,
+
+    /// Name of the library served at the root paths, defaults to the first
+    #[clap(long, env("ANSERNO_DEFAULT_LIBRARY"))]
+    pub default_library: Option<String>,
+
+    /// Name of an http(s) library whose book files and covers are streamed
+    /// through anserno instead of redirecting clients to the source
+    #[clap(
+        long = "proxy-library",
+        value_delimiter = ',',
+        env("ANSERNO_PROXY_LIBRARIES")
+    )]
+    pub proxy_libraries: Vec<String>,
+
+    /// Seconds between checks of remote library sources for changes, local
+    /// ones are watched instead, 0 disables automatic refresh
+    #[clap(long, default_value_t = 60, env("ANSERNO_REFRESH_INTERVAL"))]
+    pub refresh_interval: u64,
+
+    /// Seconds to wait for a library source to connect or send data
+    #[clap(long, default_value_t = 30, env("ANSERNO_FETCH_TIMEOUT"))]
+    pub fetch_timeout: u64,
+
+    /// Retries of an interrupted library database download
+    #[clap(long, default_value_t = 3, env("ANSERNO_FETCH_RETRIES"))]
+    pub fetch_retries: u32,
+
+    /// Largest library database accepted, in bytes
+    #[clap(long, env("ANSERNO_MAX_DATABASE_SIZE"))]
+    pub max_database_size: Option<u64>,
+
+    /// Directory caching book files and covers of remote libraries, which
+    /// are fetched from their source on every request when unset
+    #[clap(long, env("ANSERNO_CACHE_DIR"))]
+    pub cache_dir: Option<std::path::PathBuf>,
+
+    /// Total size of the cached files, in bytes
+    #[clap(long, default_value_t = 1 << 30, env("ANSERNO_CACHE_SIZE"))]
+    pub cache_size: u64,
+
+    /// Seconds a cached file is served before it is revalidated with its
+    /// source
+    #[clap(long, default_value_t = 300, env("ANSERNO_CACHE_MAX_AGE"))]
+    pub cache_max_age: u64,
+
+    /// Directory keeping the search index of each library across restarts,
+    /// so only books changed since are indexed again at startup, and the
+    /// fetched database of remote libraries, so it is only fetched again when
+    /// the source changed
+    #[clap(long, env("ANSERNO_INDEX_DIR"))]
+    pub index_dir: Option<std::path::PathBuf>,
+
+    /// Username for http basic authentication against the library sources
+    #[clap(long, env("ANSERNO_SOURCE_USERNAME"))]
+    pub source_username: Option<String>,
+
+    /// Password for http basic authentication against the library sources
+    #[clap(
+        long,
+        env("ANSERNO_SOURCE_PASSWORD"),
+        hide_env_values = true,
+        requires("source_username"),
+        conflicts_with("source_password_file")
+    )]
+    pub source_password: Option<String>,
+
+    /// File containing the password for http basic authentication
+    #[clap(long, env("ANSERNO_SOURCE_PASSWORD_FILE"), requires("source_username"))]
+    pub source_password_file: Option<std::path::PathBuf>,
+
+    /// Bearer token presented to the library sources
+    #[clap(
+        long,
+        env("ANSERNO_SOURCE_BEARER_TOKEN"),
+        hide_env_values = true,
+        conflicts_with_all(["source_username", "source_bearer_token_file"])
+    )]
+    pub source_bearer_token: Option<String>,
+
+    /// File containing the bearer token presented to the library sources
+    #[clap(
+        long,
+        env("ANSERNO_SOURCE_BEARER_TOKEN_FILE"),
+        conflicts_with("source_username")
+    )]
+    pub source_bearer_token_file: Option<std::path::PathBuf>,
+
+    /// Extra header sent to the library sources, as `Name: value`
+    #[clap(long = "source-header", value_parser = parse_header)]
+    pub source_headers: Vec<(String, String)>,
+
+    /// File of extra headers sent to the library sources, one `Name: value`
+    /// per line
+    #[clap(long, env("ANSERNO_SOURCE_HEADERS_FILE"))]
+    pub source_headers_file: Option<std::path::PathBuf>,
+
+    /// PEM file with the client certificate and private key presented to the
+    /// library sources
+    #[clap(long, env("ANSERNO_SOURCE_IDENTITY"))]
+    pub source_identity: Option<std::path::PathBuf>,
+
+    /// Endpoint of the S3 compatible service hosting `s3://` libraries, AWS
+    /// when unset
+    #[clap(long, env("ANSERNO_S3_ENDPOINT"))]
+    pub s3_endpoint: Option<url::Url>,
+
+    /// Region of the S3 compatible service
+    #[clap(long, default_value = "us-east-1", env("AWS_REGION"))]
+    pub s3_region: String,
+
+    /// Access key id for `s3://` libraries, public buckets are read
+    /// anonymously when unset
+    #[clap(long, env("AWS_ACCESS_KEY_ID"), requires("s3_secret_access_key"))]
+    pub s3_access_key_id: Option<String>,
+
+    /// Secret access key for `s3://` libraries
+    #[clap(
+        long,
+        env("AWS_SECRET_ACCESS_KEY"),
+        hide_env_values = true,
+        requires("s3_access_key_id")
+    )]
+    pub s3_secret_access_key: Option<String>,
+
+    /// Session token for temporary S3 credentials
+    #[clap(long, env("AWS_SESSION_TOKEN"), hide_env_values = true)]
+    pub s3_session_token: Option<String>,
+
+    /// Address S3 buckets as the first path segment of the endpoint, as
+    /// self hosted services usually expect, rather than as a subdomain
+    #[clap(long, env("ANSERNO_S3_PATH_STYLE"))]
+    pub s3_path_style: bool,
+
+    /// How book files and covers of `s3://` libraries are served
+    #[clap(long, value_enum, default_value_t = S3ServingArg::Presign, env("ANSERNO_S3_SERVING"))]
+    pub s3_serving: S3ServingArg,
+
+    /// Seconds presigned S3 urls stay valid
+    #[clap(long, default_value_t = 3600, env("ANSERNO_S3_PRESIGN_EXPIRY"))]
+    pub s3_presign_expiry: u64,
 
     /// Path to the anserno-core templates for html rendering
     #[clap(
@@ -42,4 +199,385 @@ pub struct Args {
         env("ANSERNO_STATIC_FILES_PATH")
     )]
     pub static_files_dir: std::path::PathBuf,
+
+    /// Serve the `/api/admin` endpoints, such as the library integrity
+    /// report. Anyone reaching `/api/admin/integrity` has anserno check every
+    /// format and cover file of the library, with a `HEAD` request for each
+    /// file of remote and S3 libraries, so keep it behind access controls
+    #[clap(long, env("ANSERNO_ENABLE_ADMIN"))]
+    pub enable_admin: bool,
+
+    /// Outbound link template for a book identifier type, as `type=template`
+    /// where `{value}` is replaced by the identifier (e.g.
+    /// `isbn=https://openlibrary.org/isbn/{value}`)
+    #[clap(
+        long = "identifier-link",
+        value_parser = parse_key_value,
+        value_delimiter = ',',
+        env("ANSERNO_IDENTIFIER_LINKS")
+    )]
+    pub identifier_links: Vec<(String, String)>,
+
+    /// Weight of a search index column when ranking full text search hits,
+    /// as `column=weight` (e.g. `description=2`), columns being title, sort,
+    /// authors, series, formats, tags, description and custom
+    #[clap(
+        long = "search-weight",
+        value_parser = parse_search_weight,
+        value_delimiter = ',',
+        env("ANSERNO_SEARCH_WEIGHTS")
+    )]
+    pub search_weights: Vec<(String, f64)>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Serve the libraries (default)
+    Serve,
+
+    /// Check that the files of the libraries match their databases, failing
+    /// when issues are found
+    Check {
+        /// Names of the libraries to check, all of them when unset
+        libraries: Vec<String>,
+
+        /// Print the reports as json
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+impl Args {
+    /// Named libraries to serve, with `--library-url` first as `default`.
+    pub fn library_sources(&self) -> Vec<(String, url::Url)> {
+        self.library_url
+            .iter()
+            .map(|url| ("default".to_string(), url.clone()))
+            .chain(self.libraries.iter().cloned())
+            .collect()
+    }
+
+    /// Settings of the resource cache, when enabled.
+    pub fn resource_cache_options(&self) -> Option<ResourceCacheOptions> {
+        self.cache_dir
+            .as_ref()
+            .map(|directory| ResourceCacheOptions {
+                directory: directory.clone(),
+                max_size: self.cache_size,
+                max_age: std::time::Duration::from_secs(self.cache_max_age),
+            })
+    }
+
+    /// Settings of the S3 compatible service hosting `s3://` libraries.
+    pub fn s3_options(&self) -> S3Options {
+        S3Options {
+            endpoint: self.s3_endpoint.clone(),
+            region: self.s3_region.clone(),
+            credentials: self
+                .s3_access_key_id
+                .iter()
+                .zip(&self.s3_secret_access_key)
+                .map(|(access_key_id, secret_access_key)| S3Credentials {
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                    session_token: self.s3_session_token.clone(),
+                })
+                .next(),
+            path_style: self.s3_path_style,
+            serving: self.s3_serving.into(),
+            presign_expiry: std::time::Duration::from_secs(self.s3_presign_expiry),
+        }
+    }
+
+    /// Credentials presented to every library source, reading secrets from
+    /// their files where given.
+    pub fn source_credentials(&self) -> crate::error::Result<Credentials> {
+        let read_secret = |path: &std::path::Path| {
+            std::fs::read_to_string(path).map(|secret| secret.trim_end().to_string())
+        };
+
+        let authorization = match (&self.source_username, &self.source_bearer_token) {
+            (Some(username), _) => Some(Authorization::Basic {
+                username: username.clone(),
+                password: match &self.source_password_file {
+                    Some(path) => Some(read_secret(path)?),
+                    None => self.source_password.clone(),
+                },
+            }),
+            (None, Some(token)) => Some(Authorization::Bearer(token.clone())),
+            (None, None) => self
+                .source_bearer_token_file
+                .as_deref()
+                .map(read_secret)
+                .transpose()?
+                .map(Authorization::Bearer),
+        };
+
+        let mut headers = self.source_headers.clone();
+
+        if let Some(path) = &self.source_headers_file {
+            for line in std::fs::read_to_string(path)?.lines() {
+                if !line.trim().is_empty() {
+                    headers.push(parse_header(line).map_err(crate::error::Error::Config)?);
+                }
+            }
+        }
+
+        let identity = self
+            .source_identity
+            .as_deref()
+            .map(std::fs::read)
+            .transpose()?;
+
+        Ok(Credentials {
+            authorization,
+            headers,
+            identity,
+        })
+    }
+}
+
+fn parse_library(value: &str) -> Result<(String, url::Url), String> {
+    let (name, url) = parse_key_value(value)?;
+
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid library name, expected [A-Za-z0-9_-]: {name}"
+        ));
+    }
+
+    url::Url::parse(&url)
+        .map(|url| (name, url))
+        .map_err(|err| format!("invalid library url {url}: {err}"))
+}
+
+/// S3 serving adapter for clap cli parser
+#[derive(::core::marker::Copy, ::std::clone::Clone, ::std::fmt::Debug, clap::ValueEnum)]
+pub enum S3ServingArg {
+    /// Redirect clients to presigned urls
+    Presign,
+    /// Stream objects through anserno
+    Stream,
+}
+
+impl From<S3ServingArg> for S3Serving {
+    fn from(value: S3ServingArg) -> Self {
+        match value {
+            S3ServingArg::Presign => Self::Presign,
+            S3ServingArg::Stream => Self::Stream,
+        }
+    }
+}
+
+fn parse_search_weight(value: &str) -> Result<(String, f64), String> {
+    let (column, weight) = parse_key_value(value)?;
+
+    weight
+        .parse::<f64>()
+        .map(|weight| (column, weight))
+        .map_err(|err| format!("invalid search weight {weight}: {err}"))
+}
+
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once(':')
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| format!("invalid header, expected `Name: value`: {value}"))
+}
+
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid key=value pair: {value}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use clap::Parser;
+
+    use super::*;
+
+    /// Parsing reads the environment, serialize tests which change it.
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    fn credentials(args: &[&str]) -> Credentials {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+
+        Args::try_parse_from(
+            ["anserno", "--library-url", "https://example.com/library"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+        .source_credentials()
+        .unwrap()
+    }
+
+    fn secret_file(contents: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn no_source_credentials() {
+        assert!(credentials(&[]).is_empty());
+    }
+
+    #[test]
+    fn basic_credentials_from_password_or_file() {
+        let basic = |password: &str| {
+            Some(Authorization::Basic {
+                username: "reader".to_string(),
+                password: Some(password.to_string()),
+            })
+        };
+
+        assert_eq!(
+            credentials(&["--source-username", "reader", "--source-password", "secret"])
+                .authorization,
+            basic("secret")
+        );
+
+        let file = secret_file("from file\n");
+        assert_eq!(
+            credentials(&[
+                "--source-username",
+                "reader",
+                "--source-password-file",
+                file.path().to_str().unwrap(),
+            ])
+            .authorization,
+            basic("from file")
+        );
+
+        assert_eq!(
+            credentials(&["--source-username", "reader"]).authorization,
+            Some(Authorization::Basic {
+                username: "reader".to_string(),
+                password: None,
+            })
+        );
+    }
+
+    #[test]
+    fn bearer_credentials_from_token_or_file() {
+        assert_eq!(
+            credentials(&["--source-bearer-token", "token"]).authorization,
+            Some(Authorization::Bearer("token".to_string()))
+        );
+
+        let file = secret_file("file-token\n");
+        assert_eq!(
+            credentials(&["--source-bearer-token-file", file.path().to_str().unwrap()])
+                .authorization,
+            Some(Authorization::Bearer("file-token".to_string()))
+        );
+    }
+
+    #[test]
+    fn credentials_from_environment() {
+        let file = secret_file("env-secret\n");
+
+        let authorization = {
+            let _environment = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+
+            std::env::set_var("ANSERNO_SOURCE_USERNAME", "env-reader");
+            std::env::set_var("ANSERNO_SOURCE_PASSWORD_FILE", file.path());
+
+            let args =
+                Args::try_parse_from(["anserno", "--library-url", "https://example.com/library"]);
+
+            std::env::remove_var("ANSERNO_SOURCE_USERNAME");
+            std::env::remove_var("ANSERNO_SOURCE_PASSWORD_FILE");
+
+            args.unwrap().source_credentials().unwrap().authorization
+        };
+
+        assert_eq!(
+            authorization,
+            Some(Authorization::Basic {
+                username: "env-reader".to_string(),
+                password: Some("env-secret".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn conflicting_authorizations_are_rejected() {
+        let _environment = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+
+        for args in [
+            &[
+                "--source-username",
+                "reader",
+                "--source-bearer-token",
+                "token",
+            ][..],
+            &[
+                "--source-bearer-token",
+                "token",
+                "--source-bearer-token-file",
+                "token.txt",
+            ],
+            &[
+                "--source-username",
+                "reader",
+                "--source-password",
+                "secret",
+                "--source-password-file",
+                "secret.txt",
+            ],
+            &["--source-password", "secret"],
+        ] {
+            assert!(
+                Args::try_parse_from(
+                    ["anserno", "--library-url", "https://example.com/library"]
+                        .iter()
+                        .chain(args)
+                )
+                .is_err(),
+                "{args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn headers_from_flags_and_file() {
+        let file = secret_file("X-Api-Key: from file\n\nX-Tenant: books\n");
+
+        assert_eq!(
+            credentials(&[
+                "--source-header",
+                "X-Api-Key: from flag",
+                "--source-headers-file",
+                file.path().to_str().unwrap(),
+            ])
+            .headers,
+            [
+                ("X-Api-Key", "from flag"),
+                ("X-Api-Key", "from file"),
+                ("X-Tenant", "books"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn identity_from_file() {
+        let file = secret_file("-----BEGIN CERTIFICATE-----\n");
+
+        assert_eq!(
+            credentials(&["--source-identity", file.path().to_str().unwrap()]).identity,
+            Some(b"-----BEGIN CERTIFICATE-----\n".to_vec())
+        );
+    }
 }
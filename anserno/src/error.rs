@@ -1,6 +1,8 @@
 #[derive(Debug)]
 pub enum Error {
     CalibreData(calibre_data::error::Error),
+    Config(String),
+    Integrity(String),
     StdIo(::std::io::Error),
     UrlParse(url::ParseError),
     Unknown,
@@ -12,6 +14,8 @@ impl ::std::fmt::Display for Error {
 
         match self {
             Self::CalibreData(err) => err.fmt(f),
+            Self::Config(msg) => write!(f, "Config Error: {msg}"),
+            Self::Integrity(msg) => write!(f, "Integrity Error: {msg}"),
             Self::StdIo(err) => err.fmt(f),
             Self::UrlParse(err) => err.fmt(f),
             Self::Unknown => write!(f, "Unknown error"),
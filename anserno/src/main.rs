@@ -1,7 +1,22 @@
+use std::sync::Arc;
+
 use actix_web::{middleware, web, App, HttpServer};
-use anserno::logging::LogFormat;
-use anserno_core::{config, context::ContextBuilder};
-use calibre_data::library::{CalibreLibrary, RemoteLibrary};
+use anserno::{
+    cli::{Args, Command},
+    logging::{LogFormat, LogLevel},
+};
+use anserno_core::{
+    config, context::ContextBuilder, identifier_links::IdentifierLinks, libraries::Libraries,
+    refresh, shared_library::SharedLibrary,
+};
+use calibre_data::{
+    integrity::IntegrityReport,
+    library::{
+        CalibreLibrary, FetchOptions, LocalLibrary, RemoteLibrary, RemoteServing, ResourceCache,
+        S3Library,
+    },
+    search::SearchWeights,
+};
 use clap::Parser;
 use tera::Tera;
 use tracing_actix_web::TracingLogger;
@@ -9,7 +24,7 @@ use tracing_subscriber::{filter, layer::SubscriberExt, util::SubscriberInitExt,
 
 #[tokio::main]
 pub async fn main() -> anserno::error::Result<()> {
-    let args = anserno::cli::Args::parse();
+    let args = Args::parse();
 
     let trace_formatter = tracing_subscriber::fmt::layer()
         .with_level(true)
@@ -29,21 +44,141 @@ pub async fn main() -> anserno::error::Result<()> {
 
     tracing::info!("Setting up web execution context");
 
-    let mut library = RemoteLibrary::new(args.library_url)?;
-    library
-        .connect_with_config(|config| {
-            config
-                .sqlx_logging(true)
-                .sqlx_logging_level(args.sqlx_log_level.into());
-        })
+    let library_sources = args.library_sources();
+
+    let default_library = match &args.default_library {
+        Some(name) => name.clone(),
+        None => library_sources
+            .first()
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| anserno::error::Error::Config("No library configured".to_string()))?,
+    };
+
+    let libraries = Libraries::new(
+        library_sources.iter().map(|(name, _)| name.clone()),
+        &default_library,
+    );
+
+    let library_names = library_sources
+        .iter()
+        .map(|(name, _)| name)
+        .collect::<::std::collections::BTreeSet<_>>();
+
+    if library_names.len() != library_sources.len() {
+        return Err(anserno::error::Error::Config(
+            "Library names must be unique".to_string(),
+        ));
+    }
+
+    if let Some(name) = args
+        .proxy_libraries
+        .iter()
+        .find(|name| !library_names.contains(name))
+    {
+        return Err(anserno::error::Error::Config(format!(
+            "Unknown proxied library: {name}"
+        )));
+    }
+
+    if libraries.default_library().is_none() {
+        return Err(anserno::error::Error::Config(format!(
+            "Unknown default library: {default_library}"
+        )));
+    }
+
+    let identifier_links = args
+        .identifier_links
+        .iter()
+        .fold(IdentifierLinks::default(), |links, (kind, template)| {
+            links.with_template(kind, template)
+        });
+
+    let search_weights = args
+        .search_weights
+        .iter()
+        .try_fold(SearchWeights::default(), |weights, (column, weight)| {
+            weights.with_weight(column, *weight)
+        })?;
+
+    let fetch_options = FetchOptions {
+        timeout: ::std::time::Duration::from_secs(args.fetch_timeout),
+        retries: args.fetch_retries,
+        max_size: args.max_database_size,
+        credentials: args.source_credentials()?,
+        ..FetchOptions::default()
+    };
+
+    let resource_cache = args
+        .resource_cache_options()
+        .map(ResourceCache::open)
+        .transpose()?
+        .map(Arc::new);
+
+    if let Some(index_dir) = &args.index_dir {
+        ::std::fs::create_dir_all(index_dir)?;
+    }
+
+    if let Some(Command::Check { libraries, json }) = &args.command {
+        return check(
+            &args,
+            library_sources,
+            libraries,
+            *json,
+            &fetch_options,
+            resource_cache.as_ref(),
+        )
+        .await;
+    }
+
+    let template_engine = Tera::new(&args.templates_glob).unwrap();
+
+    let mut contexts = Vec::new();
+    let mut default_context = None;
+
+    for (name, library_url) in library_sources {
+        let configurer = configurer(args.sqlx_log_level);
+        let library = connect_library(
+            &args,
+            &name,
+            library_url,
+            &fetch_options,
+            resource_cache.as_ref(),
+            configurer,
+        )
         .await?;
 
-    let context = ContextBuilder::default()
-        .library(library)
-        .template_engine(Tera::new(&args.templates_glob).unwrap())
-        .static_files_dir(&args.static_files_dir)
-        .build()
-        .unwrap();
+        if args.refresh_interval > 0 {
+            tokio::spawn(refresh::watch(
+                library.clone(),
+                name.clone(),
+                ::std::time::Duration::from_secs(args.refresh_interval),
+                configurer,
+            ));
+        }
+
+        let context = |url_prefix: String| {
+            ContextBuilder::default()
+                .library(library.clone())
+                .template_engine(libraries.template_engine(&template_engine, &url_prefix))
+                .static_files_dir(&args.static_files_dir)
+                .identifier_links(identifier_links.clone())
+                .library_name(&name)
+                .url_prefix(url_prefix)
+                .libraries(libraries.clone())
+                .admin(args.enable_admin)
+                .search_weights(search_weights)
+                .build()
+                .unwrap()
+        };
+
+        if name == default_library {
+            default_context = Some(context(String::new()));
+        }
+
+        contexts.push(context(Libraries::url_prefix(&name)));
+    }
+
+    let default_context = default_context.unwrap();
 
     tracing::info!(
         "Starting anserno web server on {}:{}",
@@ -55,8 +190,8 @@ pub async fn main() -> anserno::error::Result<()> {
         App::new()
             .wrap(TracingLogger::default())
             .wrap(middleware::NormalizePath::trim())
-            .app_data(web::Data::new(context.clone()))
-            .configure(config::configure)
+            .app_data(web::Data::new(default_context.clone()))
+            .configure(|service_config| config::configure_libraries(service_config, &contexts))
     })
     .bind(format!("{}:{}", &args.host, &args.port))?
     .run()
@@ -64,3 +199,152 @@ pub async fn main() -> anserno::error::Result<()> {
 
     Ok(())
 }
+
+/// Database connection options, logging queries at `sqlx_log_level`.
+fn configurer(
+    sqlx_log_level: LogLevel,
+) -> impl Fn(&mut sea_orm::ConnectOptions) + ::core::marker::Copy + Send + Sync + 'static {
+    move |config: &mut sea_orm::ConnectOptions| {
+        config
+            .sqlx_logging(true)
+            .sqlx_logging_level(sqlx_log_level.into());
+    }
+}
+
+/// Connect the library `name`. Local libraries are opened in place,
+/// anything else is fetched.
+async fn connect_library<C>(
+    args: &Args,
+    name: &str,
+    library_url: url::Url,
+    fetch_options: &FetchOptions,
+    resource_cache: Option<&Arc<ResourceCache>>,
+    configurer: C,
+) -> anserno::error::Result<SharedLibrary>
+where
+    C: FnMut(&mut sea_orm::ConnectOptions),
+{
+    tracing::info!("Connecting library {name} from {library_url}");
+
+    Ok(match library_url.scheme() {
+        "file" => {
+            let mut library = LocalLibrary::from_url(&library_url)?;
+            if let Some(index_dir) = &args.index_dir {
+                library = library.with_index_dir(index_dir);
+            }
+            library.connect_with_config(configurer).await?;
+            SharedLibrary::new(library)
+        }
+        "s3" => {
+            let mut library = S3Library::new(library_url, args.s3_options())?
+                .with_fetch_options(fetch_options.clone());
+            if let Some(index_dir) = &args.index_dir {
+                library = library.with_index_dir(index_dir);
+            }
+            if let Some(resource_cache) = resource_cache {
+                library = library.with_resource_cache(resource_cache.clone());
+            }
+            library.connect_with_config(configurer).await?;
+            SharedLibrary::new(library)
+        }
+        _ => {
+            let serving = if args.proxy_libraries.iter().any(|proxied| proxied == name) {
+                RemoteServing::Proxy
+            } else {
+                RemoteServing::Redirect
+            };
+
+            let mut library = RemoteLibrary::new(library_url)?
+                .with_fetch_options(fetch_options.clone())
+                .with_serving(serving);
+            if let Some(index_dir) = &args.index_dir {
+                library = library.with_index_dir(index_dir);
+            }
+            if let Some(resource_cache) = resource_cache {
+                library = library.with_resource_cache(resource_cache.clone());
+            }
+            library.connect_with_config(configurer).await?;
+            SharedLibrary::new(library)
+        }
+    })
+}
+
+/// Check the integrity of the `selected` libraries, all of them when empty,
+/// failing when any issue is found.
+async fn check(
+    args: &Args,
+    library_sources: Vec<(String, url::Url)>,
+    selected: &[String],
+    json: bool,
+    fetch_options: &FetchOptions,
+    resource_cache: Option<&Arc<ResourceCache>>,
+) -> anserno::error::Result<()> {
+    if let Some(name) = selected
+        .iter()
+        .find(|name| !library_sources.iter().any(|(source, _)| source == *name))
+    {
+        return Err(anserno::error::Error::Config(format!(
+            "Unknown library: {name}"
+        )));
+    }
+
+    let mut reports = ::std::collections::BTreeMap::new();
+
+    for (name, library_url) in library_sources {
+        if !selected.is_empty() && !selected.contains(&name) {
+            continue;
+        }
+
+        let library = connect_library(
+            args,
+            &name,
+            library_url,
+            fetch_options,
+            resource_cache,
+            configurer(args.sqlx_log_level),
+        )
+        .await?;
+
+        tracing::info!("Checking library {name}");
+
+        reports.insert(name, IntegrityReport::check(library.load().as_ref()).await?);
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&reports)
+                .map_err(|err| anserno::error::Error::Config(err.to_string()))?
+        );
+    } else {
+        for (name, report) in &reports {
+            println!(
+                "{name}: {} books, {} files checked, {} issues",
+                report.books,
+                report.files,
+                report.issues.len()
+            );
+
+            for issue in &report.issues {
+                println!("  {issue}");
+            }
+
+            if !report.orphans_checked {
+                println!("  orphan directories not checked, the source cannot be listed");
+            }
+        }
+    }
+
+    let issues = reports
+        .values()
+        .map(|report| report.issues.len())
+        .sum::<usize>();
+
+    if issues > 0 {
+        return Err(anserno::error::Error::Integrity(format!(
+            "{issues} issues found"
+        )));
+    }
+
+    Ok(())
+}
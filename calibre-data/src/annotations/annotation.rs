@@ -0,0 +1,92 @@
+use sea_orm::{
+    prelude::DateTimeUtc, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityName,
+    EntityTrait, QueryFilter, QueryOrder, Statement,
+};
+
+use crate::{entities::annotations, error::Result};
+
+/// Viewer payload stored as JSON in `annotations.annot_data`.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::default::Default, serde::Deserialize)]
+#[serde(default)]
+struct AnnotationData {
+    highlighted_text: Option<String>,
+    notes: Option<String>,
+    title: Option<String>,
+    toc_family_titles: Vec<String>,
+    removed: bool,
+}
+
+/// Highlight, bookmark or note made in the Calibre viewer.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct Annotation {
+    pub id: i32,
+    pub book: i32,
+    pub kind: String,
+    pub format: String,
+    pub user: String,
+    /// Highlighted text, or the title of a bookmark
+    pub text: Option<String>,
+    pub notes: Option<String>,
+    /// Innermost table of contents entry containing the annotation
+    pub chapter: Option<String>,
+    pub timestamp: Option<DateTimeUtc>,
+}
+
+impl Annotation {
+    /// Annotations of the book `book_id` in order of creation.
+    ///
+    /// Annotations removed in the viewer are kept by Calibre to sync the
+    /// deletion and are skipped, as are libraries predating annotations.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn find_by_book(conn: &DatabaseConnection, book_id: i32) -> Result<Vec<Self>> {
+        if !table_exists(conn, annotations::Entity.table_name()).await? {
+            return Ok(Vec::new());
+        }
+
+        Ok(annotations::Entity::find()
+            .filter(annotations::Column::Book.eq(book_id))
+            .order_by_asc(annotations::Column::Timestamp)
+            .all(conn)
+            .await?
+            .into_iter()
+            .filter_map(Self::from_model)
+            .collect())
+    }
+
+    fn from_model(model: annotations::Model) -> Option<Self> {
+        let data = serde_json::from_str::<AnnotationData>(&model.annot_data).unwrap_or_default();
+
+        if data.removed {
+            return None;
+        }
+
+        Some(Self {
+            id: model.id,
+            book: model.book,
+            kind: model.annot_type,
+            format: model.format,
+            user: model.user,
+            text: data.highlighted_text.or(data.title),
+            notes: data.notes.filter(|notes| !notes.is_empty()),
+            chapter: data.toc_family_titles.last().cloned(),
+            timestamp: epoch_datetime(model.timestamp),
+        })
+    }
+}
+
+/// Convert a Calibre floating point epoch to a timestamp.
+pub(crate) fn epoch_datetime(epoch: f64) -> Option<DateTimeUtc> {
+    DateTimeUtc::from_timestamp(epoch.trunc() as i64, (epoch.fract() * 1e9) as u32)
+}
+
+/// Whether `table` exists, older libraries lack the viewer tables.
+pub(crate) async fn table_exists(conn: &DatabaseConnection, table: &str) -> Result<bool> {
+    Ok(conn
+        .query_one(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [table.into()],
+        ))
+        .await?
+        .is_some())
+}
@@ -0,0 +1,5 @@
+mod annotation;
+pub use annotation::*;
+
+mod reading_position;
+pub use reading_position::*;
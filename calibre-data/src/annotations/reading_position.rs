@@ -0,0 +1,48 @@
+use sea_orm::{
+    prelude::DateTimeUtc, ColumnTrait, DatabaseConnection, EntityName, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+
+use crate::{
+    annotations::{epoch_datetime, table_exists},
+    entities::last_read_positions,
+    error::Result,
+};
+
+/// Last position reached in a book by a user on a device.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct ReadingPosition {
+    pub book: i32,
+    pub format: String,
+    pub user: String,
+    pub device: String,
+    /// Fraction of the book read, between 0 and 1
+    pub progress: f64,
+    pub timestamp: Option<DateTimeUtc>,
+}
+
+impl ReadingPosition {
+    /// Reading positions of the book `book_id`, most recent first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn find_by_book(conn: &DatabaseConnection, book_id: i32) -> Result<Vec<Self>> {
+        if !table_exists(conn, last_read_positions::Entity.table_name()).await? {
+            return Ok(Vec::new());
+        }
+
+        Ok(last_read_positions::Entity::find()
+            .filter(last_read_positions::Column::Book.eq(book_id))
+            .order_by_desc(last_read_positions::Column::Epoch)
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|model| Self {
+                book: model.book,
+                format: model.format,
+                user: model.user,
+                device: model.device,
+                progress: model.pos_frac,
+                timestamp: epoch_datetime(model.epoch),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,144 @@
+use sea_orm::{
+    sea_query::{Alias, Expr, Order, Query, SelectStatement},
+    ConnectionTrait, DatabaseConnection, QueryResult,
+};
+
+use crate::{
+    custom_columns::{CustomValue, Datatype},
+    entities::custom_columns,
+    error::{Error, Result},
+};
+
+/// A user defined Calibre column.
+///
+/// Normalized columns keep their distinct values in `custom_column_{id}` and
+/// associate them with books through `books_custom_column_{id}_link`. All
+/// other columns store one value per book directly in `custom_column_{id}`.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct CustomColumn {
+    pub id: i32,
+    pub label: String,
+    pub name: String,
+    pub datatype: Datatype,
+    pub is_multiple: bool,
+    pub normalized: bool,
+    pub display: serde_json::Value,
+}
+
+impl TryFrom<custom_columns::Model> for CustomColumn {
+    type Error = Error;
+
+    fn try_from(value: custom_columns::Model) -> Result<Self> {
+        Ok(Self {
+            id: value.id,
+            datatype: value.datatype.parse()?,
+            label: value.label,
+            name: value.name,
+            is_multiple: value.is_multiple,
+            normalized: value.normalized,
+            display: serde_json::from_str(&value.display).unwrap_or_default(),
+        })
+    }
+}
+
+impl CustomColumn {
+    /// Name of the table holding the column values.
+    pub fn table_name(&self) -> String {
+        format!("custom_column_{}", self.id)
+    }
+
+    /// Name of the table linking books to values of a normalized column.
+    pub fn link_table_name(&self) -> String {
+        format!("books_custom_column_{}_link", self.id)
+    }
+
+    /// Name of the table holding the `book` column for this custom column.
+    pub fn book_table_name(&self) -> String {
+        if self.normalized {
+            self.link_table_name()
+        } else {
+            self.table_name()
+        }
+    }
+
+    /// Query selecting `book`, `value`, and for series, `extra` for every
+    /// book with a value in this column.
+    pub fn select(&self) -> SelectStatement {
+        let table = Alias::new(self.table_name());
+        let book_table = Alias::new(self.book_table_name());
+
+        let mut query = Query::select();
+
+        query
+            .column((book_table.clone(), Alias::new("book")))
+            .column((table.clone(), Alias::new("value")))
+            .from(book_table.clone())
+            .order_by((table.clone(), Alias::new("value")), Order::Asc);
+
+        if self.normalized {
+            query.inner_join(
+                table.clone(),
+                Expr::col((table, Alias::new("id")))
+                    .equals((book_table.clone(), Alias::new("value"))),
+            );
+        }
+
+        if self.datatype == Datatype::Series {
+            query.column((book_table, Alias::new("extra")));
+        }
+
+        query
+    }
+
+    /// Value of this column for the book `book_id`.
+    pub async fn book_value(
+        &self,
+        conn: &DatabaseConnection,
+        book_id: i32,
+    ) -> Result<Option<CustomValue>> {
+        let query = self
+            .select()
+            .and_where(
+                Expr::col((Alias::new(self.book_table_name()), Alias::new("book"))).eq(book_id),
+            )
+            .to_owned();
+
+        let rows = conn
+            .query_all(conn.get_database_backend().build(&query))
+            .await?;
+
+        self.decode(&rows)
+    }
+
+    /// Decode rows produced by `select` into a typed value.
+    pub fn decode(&self, rows: &[QueryResult]) -> Result<Option<CustomValue>> {
+        let Some(row) = rows.first() else {
+            return Ok(None);
+        };
+
+        let value = match self.datatype {
+            Datatype::Comments | Datatype::Enumeration | Datatype::Text if self.is_multiple => {
+                CustomValue::Multiple(
+                    rows.iter()
+                        .map(|row| row.try_get_by_index::<String>(1))
+                        .collect::<::std::result::Result<_, _>>()?,
+                )
+            }
+            Datatype::Comments | Datatype::Enumeration | Datatype::Text => {
+                CustomValue::Text(row.try_get_by_index(1)?)
+            }
+            Datatype::Bool => CustomValue::Bool(row.try_get_by_index(1)?),
+            Datatype::Datetime => CustomValue::Datetime(row.try_get_by_index(1)?),
+            Datatype::Float => CustomValue::Float(row.try_get_by_index(1)?),
+            Datatype::Int => CustomValue::Int(row.try_get_by_index(1)?),
+            Datatype::Rating => CustomValue::Rating(row.try_get_by_index(1)?),
+            Datatype::Series => CustomValue::Series {
+                name: row.try_get_by_index(1)?,
+                index: row.try_get_by_index::<Option<f64>>(2)?.unwrap_or(1.0),
+            },
+            Datatype::Composite => return Ok(None),
+        };
+
+        Ok(Some(value))
+    }
+}
@@ -0,0 +1,21 @@
+use sea_orm::prelude::DateTimeUtc;
+
+/// Typed value of a custom column for a single book.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum CustomValue {
+    Bool(bool),
+    Datetime(DateTimeUtc),
+    Float(f64),
+    Int(i64),
+    Multiple(Vec<String>),
+    Rating(i32),
+    Series { name: String, index: f64 },
+    Text(String),
+}
+
+impl From<CustomValue> for serde_json::Value {
+    fn from(value: CustomValue) -> Self {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+}
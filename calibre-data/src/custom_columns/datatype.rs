@@ -0,0 +1,55 @@
+use crate::error::Error;
+
+/// Calibre custom column datatypes, as stored in `custom_columns.datatype`.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Datatype {
+    Bool,
+    Comments,
+    Composite,
+    Datetime,
+    Enumeration,
+    Float,
+    Int,
+    Rating,
+    Series,
+    Text,
+}
+
+impl Datatype {
+    /// True for datatypes whose values are worth adding to the search index.
+    pub fn is_searchable(&self) -> bool {
+        matches!(
+            self,
+            Self::Comments | Self::Enumeration | Self::Series | Self::Text
+        )
+    }
+}
+
+impl ::std::str::FromStr for Datatype {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bool" => Ok(Self::Bool),
+            "comments" => Ok(Self::Comments),
+            "composite" => Ok(Self::Composite),
+            "datetime" => Ok(Self::Datetime),
+            "enumeration" => Ok(Self::Enumeration),
+            "float" => Ok(Self::Float),
+            "int" => Ok(Self::Int),
+            "rating" => Ok(Self::Rating),
+            "series" => Ok(Self::Series),
+            "text" => Ok(Self::Text),
+            _ => Err(Error::CustomColumn(format!("Unknown datatype: {s}"))),
+        }
+    }
+}
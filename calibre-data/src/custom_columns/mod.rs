@@ -0,0 +1,11 @@
+mod datatype;
+pub use datatype::*;
+
+mod custom_value;
+pub use custom_value::*;
+
+mod custom_column;
+pub use custom_column::*;
+
+mod registry;
+pub use registry::*;
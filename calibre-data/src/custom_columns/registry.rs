@@ -0,0 +1,143 @@
+use std::collections::BTreeSet;
+
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityName, EntityTrait, QueryFilter,
+    QueryOrder, Statement,
+};
+
+use crate::{
+    custom_columns::{CustomColumn, CustomValue, Datatype},
+    entities::custom_columns,
+    error::Result,
+};
+
+/// A custom column value for a single book, along with its column metadata.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct CustomField {
+    pub label: String,
+    pub name: String,
+    pub datatype: Datatype,
+    pub value: CustomValue,
+}
+
+/// Registry of the custom columns defined in a Calibre library.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::default::Default, serde::Serialize)]
+pub struct CustomColumns(Vec<CustomColumn>);
+
+impl CustomColumns {
+    /// Read the custom column definitions from the library database.
+    ///
+    /// Composite columns are computed by Calibre at display time and have no
+    /// stored values, so they are skipped, as are columns pending deletion or
+    /// whose tables are missing.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn load(conn: &DatabaseConnection) -> Result<Self> {
+        let tables = conn
+            .query_all(Statement::from_string(
+                conn.get_database_backend(),
+                "SELECT name FROM sqlite_master WHERE type = 'table'",
+            ))
+            .await?
+            .iter()
+            .map(|row| row.try_get_by_index::<String>(0))
+            .collect::<::std::result::Result<BTreeSet<_>, _>>()?;
+
+        if !tables.contains(custom_columns::Entity.table_name()) {
+            return Ok(Self::default());
+        }
+
+        let mut columns = Vec::new();
+
+        for model in custom_columns::Entity::find()
+            .filter(custom_columns::Column::MarkForDelete.eq(false))
+            .order_by_asc(custom_columns::Column::Label)
+            .all(conn)
+            .await?
+        {
+            let column = match CustomColumn::try_from(model) {
+                Ok(column) => column,
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("Skipping custom column: {_err}");
+                    continue;
+                }
+            };
+
+            if column.datatype == Datatype::Composite
+                || !tables.contains(&column.table_name())
+                || (column.normalized && !tables.contains(&column.link_table_name()))
+            {
+                continue;
+            }
+
+            columns.push(column);
+        }
+
+        Ok(Self(columns))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CustomColumn> {
+        self.0.iter()
+    }
+
+    /// Lookup a custom column by its label, without the leading `#`.
+    pub fn get(&self, label: &str) -> Option<&CustomColumn> {
+        self.0.iter().find(|column| column.label == label)
+    }
+
+    /// Values of every custom column set for the book `book_id`.
+    pub async fn book_fields(
+        &self,
+        conn: &DatabaseConnection,
+        book_id: i32,
+    ) -> Result<Vec<CustomField>> {
+        let mut fields = Vec::new();
+
+        for column in self.iter() {
+            if let Some(value) = column.book_value(conn, book_id).await? {
+                fields.push(CustomField {
+                    label: column.label.clone(),
+                    name: column.name.clone(),
+                    datatype: column.datatype,
+                    value,
+                });
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Append the values of textual custom columns to the `custom` column of
+    /// the search index, for the books not indexed yet.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn)))]
+    pub async fn populate_search_index<C>(&self, conn: &C) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        let backend = conn.get_database_backend();
+
+        for column in self.iter().filter(|column| column.datatype.is_searchable()) {
+            let values = backend.build(&column.select()).to_string();
+
+            conn.execute(Statement::from_string(
+                backend,
+                format!(
+                    r#"
+                    UPDATE "anserno_search_index"
+                    SET "custom" = COALESCE("custom" || ', ', '') || (
+                        SELECT GROUP_CONCAT("value", ', ')
+                        FROM ({values}) AS "custom_values"
+                        WHERE "custom_values"."book" = "anserno_search_index"."rowid"
+                    )
+                    WHERE "rowid" IN (SELECT "book" FROM "{}")
+                    AND "rowid" NOT IN (SELECT "id" FROM "anserno_indexed_books")
+                    "#,
+                    column.book_table_name()
+                ),
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -42,6 +42,7 @@ pub struct Model {
 pub enum Relation {
     Comments,
     Formats,
+    Identifiers,
 }
 
 impl RelationTrait for Relation {
@@ -49,6 +50,7 @@ impl RelationTrait for Relation {
         match self {
             Self::Comments => Entity::has_many(super::comments::Entity).into(),
             Self::Formats => Entity::has_many(super::data::Entity).into(),
+            Self::Identifiers => Entity::has_many(super::identifiers::Entity).into(),
         }
     }
 }
@@ -65,6 +67,12 @@ impl Related<super::data::Entity> for Entity {
     }
 }
 
+impl Related<super::identifiers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Identifiers.def()
+    }
+}
+
 impl Related<super::authors::Entity> for Entity {
     fn to() -> RelationDef {
         super::books_authors_link::Relation::Author.def()
@@ -75,6 +83,36 @@ impl Related<super::authors::Entity> for Entity {
     }
 }
 
+impl Related<super::languages::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_languages_link::Relation::Language.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_languages_link::Relation::Book.def().rev())
+    }
+}
+
+impl Related<super::publishers::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_publishers_link::Relation::Publisher.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_publishers_link::Relation::Book.def().rev())
+    }
+}
+
+impl Related<super::ratings::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_ratings_link::Relation::Rating.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_ratings_link::Relation::Book.def().rev())
+    }
+}
+
 impl Related<super::series::Entity> for Entity {
     fn to() -> RelationDef {
         super::books_series_link::Relation::Series.def()
@@ -85,6 +123,16 @@ impl Related<super::series::Entity> for Entity {
     }
 }
 
+impl Related<super::tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_tags_link::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_tags_link::Relation::Book.def().rev())
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl crate::library::LibraryResource for Model {
@@ -146,6 +194,29 @@ impl AsResource for Model {
             resource = resource.with_links("authors", authors);
         }
 
+        if let Ok(publishers) = self.find_related(super::publishers::Entity).all(conn).await {
+            resource = resource.with_links("publishers", publishers);
+        }
+
+        if let Ok(identifiers) = self
+            .find_related(super::identifiers::Entity)
+            .order_by_asc(super::identifiers::Column::Kind)
+            .all(conn)
+            .await
+        {
+            resource = resource.with_property(
+                "identifiers",
+                identifiers
+                    .into_iter()
+                    .map(|identifier| (identifier.kind, identifier.val.into()))
+                    .collect::<serde_json::Map<_, _>>(),
+            );
+        }
+
+        if let Ok(Some(rating)) = self.find_related(super::ratings::Entity).one(conn).await {
+            resource = resource.with_property("rating", rating.rating);
+        }
+
         if let Ok(series) = self
             .find_related(super::series::Entity)
             .order_by_asc(super::series::Column::Sort)
@@ -155,6 +226,15 @@ impl AsResource for Model {
             resource = resource.with_links("series", series);
         }
 
+        if let Ok(tags) = self
+            .find_related(super::tags::Entity)
+            .order_by_asc(super::tags::Column::Name)
+            .all(conn)
+            .await
+        {
+            resource = resource.with_links("tags", tags);
+        }
+
         Ok(resource)
     }
 }
@@ -17,6 +17,20 @@ pub struct Model {
     pub series_index: f64,
     pub formats: serde_json::Value,
     pub description: String,
+    pub tags: serde_json::Value,
+    pub publishers: serde_json::Value,
+    #[sea_orm(nullable)]
+    pub rating: Option<i32>,
+    pub identifiers: serde_json::Value,
+    #[sea_orm(nullable)]
+    pub pubdate: Option<DateTimeUtc>,
+    #[sea_orm(nullable)]
+    pub timestamp: Option<DateTimeUtc>,
+    #[sea_orm(nullable)]
+    pub last_modified: Option<DateTimeUtc>,
+    pub has_cover: bool,
+    #[sea_orm(nullable)]
+    pub uuid: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -32,6 +46,36 @@ impl Related<super::authors::Entity> for Entity {
     }
 }
 
+impl Related<super::languages::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_languages_link::Relation::Language.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_languages_link::Relation::FlatBook.def().rev())
+    }
+}
+
+impl Related<super::publishers::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_publishers_link::Relation::Publisher.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_publishers_link::Relation::FlatBook.def().rev())
+    }
+}
+
+impl Related<super::ratings::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_ratings_link::Relation::Rating.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_ratings_link::Relation::FlatBook.def().rev())
+    }
+}
+
 impl Related<super::series::Entity> for Entity {
     fn to() -> RelationDef {
         super::books_series_link::Relation::Series.def()
@@ -42,4 +86,14 @@ impl Related<super::series::Entity> for Entity {
     }
 }
 
+impl Related<super::tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::books_tags_link::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::books_tags_link::Relation::FlatBook.def().rev())
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,12 +2,25 @@
 
 pub mod prelude;
 
+pub mod annotations;
 pub mod authors;
 pub mod books;
 pub mod books_authors_link;
+pub mod books_languages_link;
+pub mod books_publishers_link;
+pub mod books_ratings_link;
 pub mod books_series_link;
+pub mod books_tags_link;
 pub mod comments;
+pub mod custom_columns;
 pub mod data;
 pub mod flat_books;
+pub mod identifiers;
+pub mod languages;
+pub mod last_read_positions;
+pub mod preferences;
+pub mod publishers;
+pub mod ratings;
 pub mod search_index;
 pub mod series;
+pub mod tags;
@@ -1,11 +1,24 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.3
 
+pub use super::annotations::Entity as Annotations;
 pub use super::authors::Entity as Authors;
 pub use super::books::Entity as Books;
 pub use super::books_authors_link::Entity as BooksAuthorsLink;
+pub use super::books_languages_link::Entity as BooksLanguagesLink;
+pub use super::books_publishers_link::Entity as BooksPublishersLink;
+pub use super::books_ratings_link::Entity as BooksRatingsLink;
 pub use super::books_series_link::Entity as BooksSeriesLink;
+pub use super::books_tags_link::Entity as BooksTagsLink;
 pub use super::comments::Entity as Comments;
+pub use super::custom_columns::Entity as CustomColumns;
 pub use super::data::Entity as Data;
 pub use super::flat_books::Entity as FlatBook;
+pub use super::identifiers::Entity as Identifiers;
+pub use super::languages::Entity as Languages;
+pub use super::last_read_positions::Entity as LastReadPositions;
+pub use super::preferences::Entity as Preferences;
+pub use super::publishers::Entity as Publishers;
+pub use super::ratings::Entity as Ratings;
 pub use super::search_index::Entity as SearchIndex;
 pub use super::series::Entity as Series;
+pub use super::tags::Entity as Tags;
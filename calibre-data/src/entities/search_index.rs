@@ -24,8 +24,14 @@ pub struct Model {
     #[sea_orm(nullable)]
     pub formats: Option<String>,
 
+    #[sea_orm(nullable)]
+    pub tags: Option<String>,
+
     #[sea_orm(nullable)]
     pub description: Option<String>,
+
+    #[sea_orm(nullable)]
+    pub custom: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,8 +1,13 @@
 #[derive(Debug)]
 pub enum Error {
+    CustomColumn(String),
     DbErr(sea_orm::DbErr),
+    LocalLibrary(String),
     RemoteLibrary(String),
+    ResourceCache(String),
     Reqwest(reqwest::Error),
+    Schema(crate::schema::SchemaError),
+    Search(String),
     StdIo(::std::io::Error),
     Unknown,
     UrlParse(url::ParseError),
@@ -12,9 +17,14 @@ impl ::std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "calibre-data: ")?;
         match self {
+            Self::CustomColumn(msg) => write!(f, "CustomColumn Error: {msg}"),
             Self::DbErr(err) => err.fmt(f),
+            Self::LocalLibrary(msg) => write!(f, "LocalLibrary Error: {msg}"),
             Self::RemoteLibrary(msg) => write!(f, "RemoteLibrary Error: {msg}"),
+            Self::ResourceCache(msg) => write!(f, "ResourceCache Error: {msg}"),
             Self::Reqwest(err) => err.fmt(f),
+            Self::Schema(err) => write!(f, "Schema Error: {err}"),
+            Self::Search(msg) => write!(f, "Search Error: {msg}"),
             Self::StdIo(err) => err.fmt(f),
             Self::Unknown => write!(f, "Unknown error"),
             Self::UrlParse(err) => err.fmt(f),
@@ -0,0 +1,67 @@
+/// Kind of problem found by an integrity check.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Format listed in `data` without its file
+    MissingFormat,
+    /// Format file of zero bytes
+    EmptyFormat,
+    /// Book flagged with a cover without `cover.jpg`
+    MissingCover,
+    /// `cover.jpg` of zero bytes
+    EmptyCover,
+    /// Book folder not referenced by any book
+    OrphanDirectory,
+    /// File which could not be checked
+    CheckFailed,
+}
+
+/// Problem found in a library by an integrity check.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Path of the file or folder, relative to the library
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ::std::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        if let (Some(book), Some(title)) = (self.book, &self.title) {
+            write!(f, "Book {book} ({title}): ")?;
+        }
+
+        match (self.kind, &self.format) {
+            (IssueKind::MissingFormat, Some(format)) => write!(f, "missing {format} file")?,
+            (IssueKind::EmptyFormat, Some(format)) => write!(f, "empty {format} file")?,
+            (IssueKind::MissingFormat, None) => write!(f, "missing file")?,
+            (IssueKind::EmptyFormat, None) => write!(f, "empty file")?,
+            (IssueKind::MissingCover, _) => write!(f, "missing cover")?,
+            (IssueKind::EmptyCover, _) => write!(f, "empty cover")?,
+            (IssueKind::OrphanDirectory, _) => write!(f, "orphan directory")?,
+            (IssueKind::CheckFailed, _) => write!(f, "check failed")?,
+        }
+
+        write!(f, " {}", self.path)?;
+
+        if let Some(error) = &self.error {
+            write!(f, ": {error}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod issue;
+pub use issue::*;
+
+mod report;
+pub use report::*;
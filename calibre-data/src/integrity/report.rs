@@ -0,0 +1,298 @@
+use std::collections::BTreeSet;
+
+use futures_util::{stream, StreamExt};
+use sea_orm::{EntityTrait, QueryOrder};
+
+use crate::{
+    entities::{books, data},
+    error::Result,
+    integrity::{IntegrityIssue, IssueKind},
+    library::{LibraryBackend, ResourceStatus},
+};
+
+/// Files checked at once, bounding the requests made to remote sources.
+pub const INTEGRITY_CHECK_CONCURRENCY: usize = 8;
+
+/// File a book of the library is expected to have.
+struct ExpectedFile {
+    book: i32,
+    title: String,
+    book_path: String,
+    file_name: String,
+    /// Format of the file, `None` for the cover
+    format: Option<String>,
+}
+
+impl ExpectedFile {
+    fn issue(&self, kind: IssueKind, error: Option<String>) -> IntegrityIssue {
+        IntegrityIssue {
+            kind,
+            book: Some(self.book),
+            title: Some(self.title.clone()),
+            format: self.format.clone(),
+            path: format!("{}/{}", self.book_path, self.file_name),
+            error,
+        }
+    }
+}
+
+/// Outcome of checking that the files of a library match its database.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct IntegrityReport {
+    pub checked_at: chrono::DateTime<chrono::Utc>,
+    /// Books in the database
+    pub books: usize,
+    /// Format and cover files checked
+    pub files: usize,
+    /// Whether the storage could be listed to find orphan directories
+    pub orphans_checked: bool,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    /// Check every format in `data` and every flagged cover of `books` for
+    /// a matching file, and look for book folders no book refers to.
+    ///
+    /// Local files are checked on the filesystem, remote ones with `HEAD`
+    /// requests, and orphans are only looked for in storage which can be
+    /// listed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(library)))]
+    pub async fn check(library: &dyn LibraryBackend) -> Result<Self> {
+        let books = books::Entity::find()
+            .find_with_related(data::Entity)
+            .order_by_asc(books::Column::Id)
+            .all(library.conn())
+            .await?;
+
+        let expected_files = books
+            .iter()
+            .flat_map(|(book, formats)| {
+                let expected_file = |file_name: String, format: Option<String>| ExpectedFile {
+                    book: book.id,
+                    title: book.title.clone(),
+                    book_path: book.path.clone(),
+                    file_name,
+                    format,
+                };
+
+                formats
+                    .iter()
+                    .map(move |format| {
+                        expected_file(
+                            format!("{}.{}", format.name, format.format.to_lowercase()),
+                            Some(format.format.clone()),
+                        )
+                    })
+                    .chain(
+                        book.has_cover
+                            .unwrap_or_default()
+                            .then(|| expected_file("cover.jpg".to_string(), None)),
+                    )
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let files = expected_files.len();
+
+        let mut issues = stream::iter(expected_files)
+            .map(|expected_file| async move {
+                let status = library
+                    .book_resource_status(&expected_file.book_path, &expected_file.file_name)
+                    .await;
+
+                let kind = match (&status, expected_file.format.is_some()) {
+                    (Ok(ResourceStatus::Missing), true) => IssueKind::MissingFormat,
+                    (Ok(ResourceStatus::Missing), false) => IssueKind::MissingCover,
+                    (Ok(ResourceStatus::Present(Some(0))), true) => IssueKind::EmptyFormat,
+                    (Ok(ResourceStatus::Present(Some(0))), false) => IssueKind::EmptyCover,
+                    (Ok(ResourceStatus::Present(_)), _) => return None,
+                    (Err(_), _) => IssueKind::CheckFailed,
+                };
+
+                Some(expected_file.issue(kind, status.err().map(|err| err.to_string())))
+            })
+            .buffer_unordered(INTEGRITY_CHECK_CONCURRENCY)
+            .filter_map(::std::future::ready)
+            .collect::<Vec<_>>()
+            .await;
+
+        issues.sort_by(|a, b| (a.book, &a.path).cmp(&(b.book, &b.path)));
+
+        let book_paths = books
+            .iter()
+            .map(|(book, _)| book.path.as_str())
+            .collect::<BTreeSet<_>>();
+
+        let book_directories = library.book_directories().await?;
+        let orphans_checked = book_directories.is_some();
+
+        issues.extend(
+            book_directories
+                .into_iter()
+                .flatten()
+                .filter(|directory| !book_paths.contains(directory.as_str()))
+                .map(|directory| IntegrityIssue {
+                    kind: IssueKind::OrphanDirectory,
+                    book: None,
+                    title: None,
+                    format: None,
+                    path: directory,
+                    error: None,
+                }),
+        );
+
+        Ok(Self {
+            checked_at: chrono::Utc::now(),
+            books: books.len(),
+            files,
+            orphans_checked,
+            issues,
+        })
+    }
+
+    /// Whether no issue was found.
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        library::{CalibreLibrary, LocalLibrary},
+        testing,
+    };
+
+    fn write_file(library: &Path, path: &str, contents: &[u8]) {
+        let path = library.join(path);
+        ::std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        ::std::fs::write(path, contents).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_files_not_matching_the_database() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let path = directory.path();
+        testing::create_library(path).await.unwrap();
+
+        let wizard = "Ursula K. Le Guin/A Wizard of Earthsea (1)";
+        let tombs = "Ursula K. Le Guin/The Tombs of Atuan (2)";
+
+        // The PDF of book 1 is deleted and its cover truncated.
+        write_file(
+            path,
+            &format!("{wizard}/A Wizard of Earthsea - Ursula K. Le Guin.epub"),
+            b"epub",
+        );
+        write_file(path, &format!("{wizard}/cover.jpg"), b"");
+
+        // Book 2 has an empty format file, and no cover.
+        write_file(
+            path,
+            &format!("{tombs}/The Tombs of Atuan - Ursula K. Le Guin.epub"),
+            b"",
+        );
+
+        // The folder of book 3 is a file, its format cannot be checked.
+        write_file(path, "Franz Kafka/Der Process (3)", b"");
+
+        // Folders no book refers to, hidden ones aside.
+        ::std::fs::create_dir_all(path.join("Ursula K. Le Guin/The Farthest Shore (4)")).unwrap();
+        ::std::fs::create_dir_all(path.join(".caltrash/b/1")).unwrap();
+
+        let mut library = LocalLibrary::new(path).unwrap();
+        library.connect().await.unwrap();
+
+        let report = IntegrityReport::check(&library).await.unwrap();
+
+        assert_eq!(report.books, 3);
+        assert_eq!(report.files, 6);
+        assert!(report.orphans_checked);
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .map(|issue| (
+                    issue.kind,
+                    issue.book,
+                    issue.format.as_deref(),
+                    &*issue.path
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    IssueKind::MissingFormat,
+                    Some(1),
+                    Some("PDF"),
+                    &*format!("{wizard}/A Wizard of Earthsea - Ursula K. Le Guin.pdf"),
+                ),
+                (
+                    IssueKind::EmptyCover,
+                    Some(1),
+                    None,
+                    &*format!("{wizard}/cover.jpg"),
+                ),
+                (
+                    IssueKind::EmptyFormat,
+                    Some(2),
+                    Some("EPUB"),
+                    &*format!("{tombs}/The Tombs of Atuan - Ursula K. Le Guin.epub"),
+                ),
+                (
+                    IssueKind::MissingCover,
+                    Some(2),
+                    None,
+                    &*format!("{tombs}/cover.jpg"),
+                ),
+                (
+                    IssueKind::CheckFailed,
+                    Some(3),
+                    Some("EPUB"),
+                    "Franz Kafka/Der Process (3)/Der Process - Franz Kafka.epub",
+                ),
+                (
+                    IssueKind::OrphanDirectory,
+                    None,
+                    None,
+                    "Ursula K. Le Guin/The Farthest Shore (4)",
+                ),
+            ]
+        );
+        assert!(report
+            .issues
+            .iter()
+            .all(|issue| issue.error.is_some() == (issue.kind == IssueKind::CheckFailed)));
+        assert!(!report.is_clean());
+    }
+
+    #[tokio::test]
+    async fn clean_libraries_have_no_issues() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let path = directory.path();
+        testing::create_library(path).await.unwrap();
+
+        for file in [
+            "Ursula K. Le Guin/A Wizard of Earthsea (1)/A Wizard of Earthsea - Ursula K. Le Guin.epub",
+            "Ursula K. Le Guin/A Wizard of Earthsea (1)/A Wizard of Earthsea - Ursula K. Le Guin.pdf",
+            "Ursula K. Le Guin/A Wizard of Earthsea (1)/cover.jpg",
+            "Ursula K. Le Guin/The Tombs of Atuan (2)/The Tombs of Atuan - Ursula K. Le Guin.epub",
+            "Ursula K. Le Guin/The Tombs of Atuan (2)/cover.jpg",
+            "Franz Kafka/Der Process (3)/Der Process - Franz Kafka.epub",
+        ] {
+            write_file(path, file, b"content");
+        }
+
+        let mut library = LocalLibrary::new(path).unwrap();
+        library.connect().await.unwrap();
+
+        let report = IntegrityReport::check(&library).await.unwrap();
+
+        assert_eq!(report.files, 6);
+        assert!(report.orphans_checked);
+        assert!(report.is_clean(), "{:?}", report.issues);
+    }
+}
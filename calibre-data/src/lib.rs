@@ -1,6 +1,15 @@
+pub mod annotations;
+pub mod custom_columns;
 pub mod entities;
 pub mod error;
+pub mod integrity;
 pub mod library;
 pub mod pagination;
 pub mod queries;
 pub mod query;
+pub mod schema;
+pub mod search;
+pub mod shelves;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use crate::{entities::flat_books, error::Result};
+use crate::{custom_columns::CustomColumns, entities::flat_books, error::Result};
 
 pub trait LibraryResource {
     fn path(&self) -> Option<PathBuf>;
@@ -36,6 +36,9 @@ pub trait CalibreLibrary {
     /// Current database connection
     fn conn(&self) -> &sea_orm::DatabaseConnection;
 
+    /// Custom columns discovered when connecting to the database
+    fn custom_columns(&self) -> &CustomColumns;
+
     /// Internal file path of a given resource
     fn resource_path(&self, resource_name: &str) -> Result<Self::ResourcePath>;
 
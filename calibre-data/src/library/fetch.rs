@@ -0,0 +1,615 @@
+use std::{
+    io::{Read, Seek, Write},
+    path::Path,
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use reqwest::{header, StatusCode};
+
+use crate::{
+    error::{Error, Result},
+    library::{Credentials, SourceVersion, REMOTE_LIBRARY_USER_AGENT},
+};
+
+/// Leading bytes of every SQLite database file.
+pub const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Longest `Retry-After` delay honoured, so a busy source does not hold up a
+/// fetch indefinitely.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Limits applied when fetching a library database.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub struct FetchOptions {
+    /// Timeout for connecting, and for each read once connected
+    pub timeout: Duration,
+    /// Attempts made after a failed transfer
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each following one
+    pub backoff: Duration,
+    /// Largest database accepted, in bytes
+    pub max_size: Option<u64>,
+    /// Credentials presented to the source
+    pub credentials: Credentials,
+}
+
+impl ::std::default::Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            max_size: None,
+            credentials: Credentials::default(),
+        }
+    }
+}
+
+impl FetchOptions {
+    /// Http client honouring the timeouts and presenting the credentials.
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let builder = reqwest::ClientBuilder::default()
+            .user_agent(REMOTE_LIBRARY_USER_AGENT)
+            .connect_timeout(self.timeout)
+            .read_timeout(self.timeout);
+
+        Ok(self.credentials.configure(builder)?.build()?)
+    }
+
+    fn check_size(&self, size: u64) -> Result<()> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(Error::RemoteLibrary(format!(
+                "Database size {size} exceeds the limit of {max_size} bytes"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(::std::fmt::Debug)]
+pub enum FetchOutcome {
+    /// The source still matches the previously fetched version.
+    NotModified,
+    /// The database was written to the target, along with its version.
+    Fetched(Option<SourceVersion>),
+}
+
+/// Result of a single http transfer attempt.
+enum Attempt {
+    Done(FetchOutcome),
+    /// Retry after the delay asked for by the source, if any, or the backoff.
+    Retry(Error, Option<Duration>),
+    /// Start over with a full transfer, the partial one being unusable.
+    Restart,
+}
+
+/// Fetch the database at `source` into `target`.
+///
+/// The transfer goes to a `.part` file next to `target`, which only replaces
+/// `target` once it is complete and starts with the SQLite header. When
+/// `previous` is given the source is only fetched if it changed since.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(source, options), fields(source = %redacted(source)))
+)]
+pub async fn fetch_database(
+    source: &url::Url,
+    target: &Path,
+    previous: Option<&SourceVersion>,
+    options: &FetchOptions,
+) -> Result<FetchOutcome> {
+    let part = target.with_extension("db.part");
+
+    let outcome = match source.origin() {
+        url::Origin::Opaque(_) => fetch_file(source, &part, previous, options)?,
+        url::Origin::Tuple(_, _, _) => fetch_http(source, &part, previous, options).await?,
+    };
+
+    if let FetchOutcome::Fetched(_) = outcome {
+        verify_sqlite_header(&part)?;
+        ::std::fs::rename(&part, target)?;
+    }
+
+    Ok(outcome)
+}
+
+fn fetch_file(
+    source: &url::Url,
+    part: &Path,
+    previous: Option<&SourceVersion>,
+    options: &FetchOptions,
+) -> Result<FetchOutcome> {
+    let source_path = source.to_file_path().map_err(|_| {
+        Error::RemoteLibrary(format!("Failed rendering database source: {source:?}"))
+    })?;
+
+    let metadata = ::std::fs::metadata(&source_path)?;
+    options.check_size(metadata.len())?;
+
+    // Read the version before copying, a change racing the copy is then
+    // picked up by the next refresh rather than missed.
+    let version = SourceVersion::from_metadata(&metadata);
+
+    if previous.is_some() && previous == version.as_ref() {
+        return Ok(FetchOutcome::NotModified);
+    }
+
+    ::std::fs::copy(source_path, part)?;
+
+    Ok(FetchOutcome::Fetched(version))
+}
+
+async fn fetch_http(
+    source: &url::Url,
+    part: &Path,
+    previous: Option<&SourceVersion>,
+    options: &FetchOptions,
+) -> Result<FetchOutcome> {
+    let client = options.client()?;
+
+    let mut target = ::std::fs::File::create(part)?;
+    let mut version = None;
+    let mut attempt = 0;
+
+    loop {
+        match fetch_http_attempt(
+            &client,
+            source,
+            &mut target,
+            previous,
+            &mut version,
+            options,
+        )
+        .await?
+        {
+            Attempt::Done(outcome) => return Ok(outcome),
+
+            Attempt::Restart => continue,
+
+            Attempt::Retry(err, retry_after) if attempt < options.retries => {
+                let delay = retry_after.unwrap_or(options.backoff * 2u32.saturating_pow(attempt));
+                attempt += 1;
+
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Fetching {} failed, retry {attempt}/{} in {delay:?}: {err}",
+                    redacted(source),
+                    options.retries
+                );
+                #[cfg(not(feature = "tracing"))]
+                let _ = err;
+
+                tokio::time::sleep(delay).await;
+            }
+
+            Attempt::Retry(err, _) => return Err(err),
+        }
+    }
+}
+
+/// Single transfer, resuming after the bytes already in `target` when the
+/// server supports ranges. Errors worth retrying are returned as
+/// `Attempt::Retry`, others fail the fetch.
+async fn fetch_http_attempt(
+    client: &reqwest::Client,
+    source: &url::Url,
+    target: &mut ::std::fs::File,
+    previous: Option<&SourceVersion>,
+    version: &mut Option<SourceVersion>,
+    options: &FetchOptions,
+) -> Result<Attempt> {
+    let written = target.stream_position()?;
+
+    let mut request = client.get(source.clone());
+
+    match (written, &version) {
+        (0, _) => {
+            if let Some(etag) = previous.and_then(|previous| previous.etag.as_deref()) {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = previous.and_then(|previous| previous.modified.as_deref()) {
+                request = request.header(header::IF_MODIFIED_SINCE, modified);
+            }
+        }
+
+        (written, Some(current)) => {
+            request = request.header(header::RANGE, format!("bytes={written}-"));
+            if let Some(validator) = current.validator() {
+                request = request.header(header::IF_RANGE, validator);
+            }
+        }
+
+        // Without a validator a resumed transfer could splice two versions.
+        (_, None) => {
+            target.set_len(0)?;
+            target.rewind()?;
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => return Ok(Attempt::Retry(err.without_url().into(), None)),
+    };
+
+    match response.status() {
+        StatusCode::NOT_MODIFIED if previous.is_some() => {
+            return Ok(Attempt::Done(FetchOutcome::NotModified))
+        }
+
+        StatusCode::PARTIAL_CONTENT if written > 0 => {
+            let range = response
+                .headers()
+                .get(header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_range);
+
+            if let Some((_, Some(size))) = range {
+                options.check_size(size)?;
+            }
+
+            // Anything but the rest of what was written would corrupt it.
+            if range.map(|(start, _)| start) != Some(written) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Fetching {} resumed at {range:?} instead of byte {written}, restarting",
+                    redacted(source)
+                );
+
+                target.set_len(0)?;
+                target.rewind()?;
+                *version = None;
+
+                return Ok(Attempt::Restart);
+            }
+        }
+
+        StatusCode::OK => {
+            target.set_len(0)?;
+            target.rewind()?;
+            *version = SourceVersion::from_headers(response.headers());
+        }
+
+        status
+            if status.is_server_error()
+                || status == StatusCode::REQUEST_TIMEOUT
+                || status == StatusCode::TOO_MANY_REQUESTS =>
+        {
+            return Ok(Attempt::Retry(
+                Error::RemoteLibrary(format!(
+                    "Fetching {} failed with status {status}",
+                    redacted(source)
+                )),
+                response
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(retry_after)
+                    .map(|delay| delay.min(MAX_RETRY_AFTER)),
+            ))
+        }
+
+        status => {
+            return Err(Error::RemoteLibrary(format!(
+                "Fetching {} failed with status {status}",
+                redacted(source)
+            )))
+        }
+    }
+
+    let offset = target.stream_position()?;
+
+    if let Some(length) = response.content_length() {
+        options.check_size(offset + length)?;
+    }
+
+    let mut bytes_stream = response.bytes_stream();
+    let mut total_written = offset;
+
+    while let Some(chunk) = bytes_stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => return Ok(Attempt::Retry(err.without_url().into(), None)),
+        };
+
+        total_written += chunk.len() as u64;
+        options.check_size(total_written)?;
+
+        target.write_all(&chunk)?;
+    }
+
+    target.flush()?;
+
+    Ok(Attempt::Done(FetchOutcome::Fetched(version.clone())))
+}
+
+/// First byte and complete size, when known, of a `bytes start-end/size`
+/// content range.
+fn content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, size) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+
+    Some((start.trim().parse().ok()?, size.trim().parse().ok()))
+}
+
+/// Delay of a `Retry-After` header, given in seconds or as an http date.
+fn retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn verify_sqlite_header(path: &Path) -> Result<()> {
+    let mut header = [0; SQLITE_HEADER.len()];
+
+    ::std::fs::File::open(path)?
+        .read_exact(&mut header)
+        .ok()
+        .filter(|_| &header == SQLITE_HEADER)
+        .ok_or_else(|| {
+            Error::RemoteLibrary("Fetched database is not a SQLite database".to_string())
+        })
+}
+
+/// `url` without its query, which may carry credentials such as a presigned
+/// signature.
+fn redacted(url: &url::Url) -> url::Url {
+    let mut url = url.clone();
+    url.set_query(None);
+    url
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Scripted response: status line and headers, body, and how many bytes
+    /// of the body are sent before the connection drops.
+    pub(crate) struct Response {
+        head: String,
+        body: Vec<u8>,
+        sent: usize,
+    }
+
+    impl Response {
+        pub(crate) fn new(status: &str, headers: &[&str], body: &[u8]) -> Self {
+            Self {
+                head: format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n",
+                    body.len(),
+                    headers
+                        .iter()
+                        .map(|header| format!("{header}\r\n"))
+                        .collect::<String>()
+                ),
+                body: body.to_vec(),
+                sent: body.len(),
+            }
+        }
+
+        fn interrupted_after(self, sent: usize) -> Self {
+            Self { sent, ..self }
+        }
+    }
+
+    /// Serve `responses` to one connection each, returning the url served
+    /// and the heads of the requests received, lowercased.
+    pub(crate) async fn serve(responses: Vec<Response>) -> (url::Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0; 1];
+                    if stream.read(&mut byte).await.unwrap() == 0 {
+                        break;
+                    }
+                    head.push(byte[0]);
+                }
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&head).to_lowercase());
+
+                stream.write_all(response.head.as_bytes()).await.unwrap();
+                stream
+                    .write_all(&response.body[..response.sent])
+                    .await
+                    .unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        let url = format!("http://{address}/library/metadata.db")
+            .parse()
+            .unwrap();
+
+        (url, requests)
+    }
+
+    fn options() -> FetchOptions {
+        FetchOptions {
+            timeout: Duration::from_secs(5),
+            backoff: Duration::from_millis(10),
+            ..FetchOptions::default()
+        }
+    }
+
+    fn database() -> Vec<u8> {
+        let mut database = SQLITE_HEADER.to_vec();
+        database.extend((0..1000).map(|byte| byte as u8));
+        database
+    }
+
+    #[tokio::test]
+    async fn rejects_non_sqlite_databases() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+        let (url, _) = serve(vec![Response::new("200 OK", &[], b"<html>login</html>")]).await;
+
+        let err = fetch_database(&url, &target, None, &options()).await;
+
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("not a SQLite database"));
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn not_modified_since_previous_version() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+        let (url, requests) = serve(vec![Response::new("304 Not Modified", &[], b"")]).await;
+
+        let previous = SourceVersion {
+            modified: None,
+            etag: Some("\"v1\"".to_string()),
+            length: None,
+        };
+
+        let outcome = fetch_database(&url, &target, Some(&previous), &options())
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome, FetchOutcome::NotModified));
+        assert!(requests.lock().unwrap()[0].contains("if-none-match: \"v1\""));
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn resumes_interrupted_transfers() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+        let database = database();
+        let length = database.len();
+
+        let (url, requests) = serve(vec![
+            Response::new("200 OK", &["etag: \"v1\""], &database).interrupted_after(400),
+            Response::new(
+                "206 Partial Content",
+                &[
+                    "etag: \"v1\"",
+                    &format!("content-range: bytes 400-{}/{length}", length - 1),
+                ],
+                &database[400..],
+            ),
+        ])
+        .await;
+
+        let outcome = fetch_database(&url, &target, None, &options())
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome, FetchOutcome::Fetched(Some(_))));
+        assert_eq!(::std::fs::read(&target).unwrap(), database);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[1].contains("range: bytes=400-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn restarts_transfers_resumed_elsewhere() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+        let database = database();
+        let length = database.len();
+
+        let (url, requests) = serve(vec![
+            Response::new("200 OK", &["etag: \"v1\""], &database).interrupted_after(400),
+            Response::new(
+                "206 Partial Content",
+                &[
+                    "etag: \"v1\"",
+                    &format!("content-range: bytes 0-{}/{length}", length - 1),
+                ],
+                &database,
+            ),
+            Response::new("200 OK", &["etag: \"v1\""], &database),
+        ])
+        .await;
+
+        fetch_database(&url, &target, None, &options())
+            .await
+            .unwrap();
+
+        assert_eq!(::std::fs::read(&target).unwrap(), database);
+        assert!(!requests.lock().unwrap()[2].contains("range:"));
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_requests() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+        let database = database();
+
+        let (url, requests) = serve(vec![
+            Response::new("429 Too Many Requests", &["retry-after: 0"], b""),
+            Response::new("408 Request Timeout", &[], b""),
+            Response::new("200 OK", &[], &database),
+        ])
+        .await;
+
+        fetch_database(&url, &target, None, &options())
+            .await
+            .unwrap();
+
+        assert_eq!(::std::fs::read(&target).unwrap(), database);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn enforces_size_limit() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let target = directory.path().join("metadata.db");
+
+        let (url, requests) = serve(vec![Response::new("200 OK", &[], &database())]).await;
+
+        let options = FetchOptions {
+            max_size: Some(100),
+            ..options()
+        };
+
+        let err = fetch_database(&url, &target, None, &options).await;
+
+        assert!(err.unwrap_err().to_string().contains("exceeds the limit"));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn parse_headers() {
+        assert_eq!(content_range("bytes 400-999/1000"), Some((400, Some(1000))));
+        assert_eq!(content_range("bytes 400-999/*"), Some((400, None)));
+        assert_eq!(content_range("items 1-2/3"), None);
+
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after("soon"), None);
+    }
+}
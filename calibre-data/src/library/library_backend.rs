@@ -0,0 +1,390 @@
+use std::{path::PathBuf, sync::Arc};
+
+use futures_util::{future::BoxFuture, stream::BoxStream, StreamExt};
+
+use crate::{
+    custom_columns::CustomColumns,
+    entities::flat_books,
+    error::{Error, Result},
+    library::{CalibreLibrary, LocalLibrary, RemoteLibrary, RemoteServing, S3Library, S3Serving},
+};
+
+/// Connection options hook used when a library reconnects.
+pub type LibraryConfigurer = dyn Fn(&mut sea_orm::ConnectOptions) + Send + Sync;
+
+/// Headers of a client request for a resource which are passed on when the
+/// resource is streamed, so ranges and conditional requests keep working.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::default::Default)]
+pub struct ResourceRequest {
+    pub range: Option<String>,
+    pub if_range: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+impl ResourceRequest {
+    /// Headers to pass on, by name.
+    pub fn headers(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("range", &self.range),
+            ("if-range", &self.if_range),
+            ("if-none-match", &self.if_none_match),
+            ("if-modified-since", &self.if_modified_since),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (name, value)))
+    }
+}
+
+/// Body of a resource served through anserno, along with the status and
+/// metadata worth passing on to the client.
+pub struct ResourceStream {
+    /// Http status, partial or not modified responses to ranges and
+    /// conditional requests
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_range: Option<String>,
+    pub accept_ranges: Option<String>,
+    pub body: BoxStream<'static, Result<bytes::Bytes>>,
+}
+
+impl ::std::fmt::Debug for ResourceStream {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        f.debug_struct("ResourceStream")
+            .field("status", &self.status)
+            .field("content_type", &self.content_type)
+            .field("content_length", &self.content_length)
+            .field("etag", &self.etag)
+            .field("last_modified", &self.last_modified)
+            .field("content_range", &self.content_range)
+            .field("accept_ranges", &self.accept_ranges)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<reqwest::Response> for ResourceStream {
+    fn from(response: reqwest::Response) -> Self {
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            status: response.status().as_u16(),
+            content_type: header(reqwest::header::CONTENT_TYPE),
+            content_length: response.content_length(),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            content_range: header(reqwest::header::CONTENT_RANGE),
+            accept_ranges: header(reqwest::header::ACCEPT_RANGES),
+            body: response.bytes_stream().map(|chunk| Ok(chunk?)).boxed(),
+        }
+    }
+}
+
+/// Where a library resource is served from.
+#[derive(::std::fmt::Debug)]
+pub enum ResolvedResource {
+    /// File on the local filesystem
+    Path(PathBuf),
+    /// Location clients can fetch the resource from themselves
+    Url(url::Url),
+    /// Body streamed through anserno
+    Stream(ResourceStream),
+}
+
+/// Whether a library resource exists, as seen by its storage.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+)]
+pub enum ResourceStatus {
+    Missing,
+    /// Present, with its size when the storage reports it
+    Present(Option<u64>),
+}
+
+/// Connected library as served by anserno, independent of its storage.
+///
+/// Unlike `CalibreLibrary` this trait is object safe, so libraries with
+/// different backends can be held as `Arc<dyn LibraryBackend>`. Implement it
+/// alongside `CalibreLibrary` to serve a custom storage backend.
+pub trait LibraryBackend: ::std::fmt::Debug + ::core::marker::Send + ::core::marker::Sync {
+    /// Location of the library, for display
+    fn source(&self) -> String;
+
+    /// Current database connection
+    fn conn(&self) -> &sea_orm::DatabaseConnection;
+
+    /// Custom columns discovered when connecting to the database
+    fn custom_columns(&self) -> &CustomColumns;
+
+    /// Resolve a flat_book's resource to where it is served from, `request`
+    /// applying to resources which are streamed
+    fn flat_book_resource<'a>(
+        &'a self,
+        flat_book: &'a flat_books::Model,
+        resource_name: &'a str,
+        request: &'a ResourceRequest,
+    ) -> BoxFuture<'a, Result<ResolvedResource>>;
+
+    /// Status of a resource in the folder of the book at `book_path`
+    fn book_resource_status<'a>(
+        &'a self,
+        book_path: &'a str,
+        resource_name: &'a str,
+    ) -> BoxFuture<'a, Result<ResourceStatus>>;
+
+    /// Book folders of the library as `author/title` paths, `None` when the
+    /// storage cannot be listed
+    fn book_directories(&self) -> BoxFuture<'_, Result<Option<Vec<String>>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Path of the source `metadata.db` when it is on the local filesystem,
+    /// so its changes can be watched rather than polled
+    fn local_database(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether the library changed since it was connected
+    fn is_stale(&self) -> BoxFuture<'_, Result<bool>>;
+
+    /// Connect to the library again, `None` when it did not change
+    fn refreshed<'a>(
+        &'a self,
+        configurer: &'a LibraryConfigurer,
+    ) -> BoxFuture<'a, Result<Option<Arc<dyn LibraryBackend>>>>;
+}
+
+impl LibraryBackend for RemoteLibrary {
+    fn source(&self) -> String {
+        RemoteLibrary::source(self).to_string()
+    }
+
+    #[inline]
+    fn conn(&self) -> &sea_orm::DatabaseConnection {
+        CalibreLibrary::conn(self)
+    }
+
+    #[inline]
+    fn custom_columns(&self) -> &CustomColumns {
+        CalibreLibrary::custom_columns(self)
+    }
+
+    /// Cached sources are served from the cache. Otherwise sources requiring
+    /// credentials are streamed, since clients cannot present them, as are
+    /// proxied sources. Others are left for the client to fetch.
+    fn flat_book_resource<'a>(
+        &'a self,
+        flat_book: &'a flat_books::Model,
+        resource_name: &'a str,
+        request: &'a ResourceRequest,
+    ) -> BoxFuture<'a, Result<ResolvedResource>> {
+        Box::pin(async move {
+            let resource = self.flat_book_resource_path(flat_book, resource_name)?;
+
+            match resource.origin() {
+                url::Origin::Opaque(_) => resource
+                    .to_file_path()
+                    .map(ResolvedResource::Path)
+                    .map_err(|_| {
+                        Error::RemoteLibrary(format!("Failed rendering resource path: {resource}"))
+                    }),
+
+                url::Origin::Tuple(_, _, _) => match self.resource_cache() {
+                    Some(resource_cache) => resource_cache.resolve(self, &resource).await,
+
+                    None if self.is_authenticated() || self.serving() == RemoteServing::Proxy => {
+                        Ok(ResolvedResource::Stream(
+                            self.get_resource(&resource, request).await?.into(),
+                        ))
+                    }
+
+                    None => Ok(ResolvedResource::Url(resource)),
+                },
+            }
+        })
+    }
+
+    fn book_resource_status<'a>(
+        &'a self,
+        book_path: &'a str,
+        resource_name: &'a str,
+    ) -> BoxFuture<'a, Result<ResourceStatus>> {
+        Box::pin(async move {
+            self.resource_status(&self.book_resource_path(book_path, resource_name)?)
+                .await
+        })
+    }
+
+    fn local_database(&self) -> Option<PathBuf> {
+        match self.source().scheme() {
+            "file" => self
+                .source()
+                .to_file_path()
+                .ok()
+                .map(|path| path.join("metadata.db")),
+            _ => None,
+        }
+    }
+
+    fn is_stale(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(RemoteLibrary::is_stale(self))
+    }
+
+    fn refreshed<'a>(
+        &'a self,
+        configurer: &'a LibraryConfigurer,
+    ) -> BoxFuture<'a, Result<Option<Arc<dyn LibraryBackend>>>> {
+        Box::pin(async move {
+            Ok(RemoteLibrary::refreshed(self, configurer)
+                .await?
+                .map(|library| Arc::new(library) as Arc<dyn LibraryBackend>))
+        })
+    }
+}
+
+impl LibraryBackend for LocalLibrary {
+    fn source(&self) -> String {
+        self.path().display().to_string()
+    }
+
+    #[inline]
+    fn conn(&self) -> &sea_orm::DatabaseConnection {
+        CalibreLibrary::conn(self)
+    }
+
+    #[inline]
+    fn custom_columns(&self) -> &CustomColumns {
+        CalibreLibrary::custom_columns(self)
+    }
+
+    fn flat_book_resource<'a>(
+        &'a self,
+        flat_book: &'a flat_books::Model,
+        resource_name: &'a str,
+        // Local files get their own range and conditional request handling.
+        _request: &'a ResourceRequest,
+    ) -> BoxFuture<'a, Result<ResolvedResource>> {
+        Box::pin(async move {
+            Ok(ResolvedResource::Path(
+                self.flat_book_resource_path(flat_book, resource_name)?,
+            ))
+        })
+    }
+
+    fn book_resource_status<'a>(
+        &'a self,
+        book_path: &'a str,
+        resource_name: &'a str,
+    ) -> BoxFuture<'a, Result<ResourceStatus>> {
+        Box::pin(async move {
+            match ::std::fs::metadata(self.book_resource_path(book_path, resource_name)) {
+                Ok(metadata) => Ok(ResourceStatus::Present(Some(metadata.len()))),
+                Err(err) if err.kind() == ::std::io::ErrorKind::NotFound => {
+                    Ok(ResourceStatus::Missing)
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn book_directories(&self) -> BoxFuture<'_, Result<Option<Vec<String>>>> {
+        Box::pin(async move { Ok(Some(LocalLibrary::book_directories(self)?)) })
+    }
+
+    fn local_database(&self) -> Option<PathBuf> {
+        Some(self.database())
+    }
+
+    fn is_stale(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(LocalLibrary::is_stale(self))
+    }
+
+    fn refreshed<'a>(
+        &'a self,
+        configurer: &'a LibraryConfigurer,
+    ) -> BoxFuture<'a, Result<Option<Arc<dyn LibraryBackend>>>> {
+        Box::pin(async move {
+            Ok(LocalLibrary::refreshed(self, configurer)
+                .await?
+                .map(|library| Arc::new(library) as Arc<dyn LibraryBackend>))
+        })
+    }
+}
+
+impl LibraryBackend for S3Library {
+    fn source(&self) -> String {
+        S3Library::source(self).to_string()
+    }
+
+    #[inline]
+    fn conn(&self) -> &sea_orm::DatabaseConnection {
+        CalibreLibrary::conn(self)
+    }
+
+    #[inline]
+    fn custom_columns(&self) -> &CustomColumns {
+        CalibreLibrary::custom_columns(self)
+    }
+
+    fn flat_book_resource<'a>(
+        &'a self,
+        flat_book: &'a flat_books::Model,
+        resource_name: &'a str,
+        request: &'a ResourceRequest,
+    ) -> BoxFuture<'a, Result<ResolvedResource>> {
+        Box::pin(async move {
+            let resource = self.flat_book_resource_path(flat_book, resource_name)?;
+
+            if let Some(resource_cache) = self.remote_library().resource_cache() {
+                return resource_cache
+                    .resolve(self.remote_library(), &resource)
+                    .await;
+            }
+
+            Ok(match self.serving() {
+                S3Serving::Presign => ResolvedResource::Url(self.presigned(&resource)?),
+                S3Serving::Stream => ResolvedResource::Stream(
+                    self.remote_library()
+                        .get_resource(&resource, request)
+                        .await?
+                        .into(),
+                ),
+            })
+        })
+    }
+
+    fn book_resource_status<'a>(
+        &'a self,
+        book_path: &'a str,
+        resource_name: &'a str,
+    ) -> BoxFuture<'a, Result<ResourceStatus>> {
+        LibraryBackend::book_resource_status(self.remote_library(), book_path, resource_name)
+    }
+
+    fn is_stale(&self) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(self.remote_library().is_stale())
+    }
+
+    fn refreshed<'a>(
+        &'a self,
+        configurer: &'a LibraryConfigurer,
+    ) -> BoxFuture<'a, Result<Option<Arc<dyn LibraryBackend>>>> {
+        Box::pin(async move {
+            Ok(S3Library::refreshed(self, configurer)
+                .await?
+                .map(|library| Arc::new(library) as Arc<dyn LibraryBackend>))
+        })
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    custom_columns::CustomColumns,
+    entities::flat_books,
+    error::{Error, Result},
+    library::{
+        connect_with_sidecar, sidecar_path, CalibreLibrary, DatabaseAccess, SearchIndexUpdate,
+        SourceVersion,
+    },
+    schema::Schema,
+};
+
+/// Calibre library on the local filesystem.
+///
+/// `metadata.db` is opened in place as a read-only database, which Calibre
+/// may keep writing to.
+/// Anserno's search index lives in a separate sidecar database attached to
+/// every connection, and the flat_books view is created as a temporary view
+/// of each connection, leaving the library itself untouched.
+#[derive(::std::fmt::Debug, ::std::clone::Clone)]
+pub struct LocalLibrary {
+    path: PathBuf,
+    tempdir: ::std::sync::Arc<tempfile::TempDir>,
+    conn: Option<sea_orm::DatabaseConnection>,
+    custom_columns: CustomColumns,
+    source_version: Option<SourceVersion>,
+    index_dir: Option<PathBuf>,
+}
+
+impl LocalLibrary {
+    /// Create a new local library from the path of its folder.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            path: path.into(),
+            tempdir: ::std::sync::Arc::new(tempfile::TempDir::new()?),
+            conn: None,
+            custom_columns: CustomColumns::default(),
+            source_version: None,
+            index_dir: None,
+        })
+    }
+
+    /// Keep the sidecar database in `index_dir`, so its search index is
+    /// reused across restarts rather than built again.
+    pub fn with_index_dir(self, index_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            index_dir: Some(index_dir.into()),
+            ..self
+        }
+    }
+
+    /// Create a new local library from a `file://` url.
+    pub fn from_url(source: &url::Url) -> Result<Self> {
+        Self::new(source.to_file_path().map_err(|_| {
+            Error::LocalLibrary(format!("Failed rendering library path: {source:?}"))
+        })?)
+    }
+
+    /// Path of the sidecar database holding anserno's own tables.
+    pub fn sidecar(&self) -> PathBuf {
+        let source = self
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| self.path.clone());
+
+        sidecar_path(
+            self.index_dir.as_deref(),
+            self.tempdir.path(),
+            &source.to_string_lossy(),
+        )
+    }
+
+    /// Version of the database at the time it was opened.
+    #[inline]
+    pub fn source_version(&self) -> Option<&SourceVersion> {
+        self.source_version.as_ref()
+    }
+
+    /// Path of a resource in the folder of the book at `book_path`.
+    pub fn book_resource_path(&self, book_path: &str, resource_name: &str) -> PathBuf {
+        self.path.join(book_path).join(resource_name)
+    }
+
+    /// Book folders of the library as `author/title` paths. Hidden folders,
+    /// such as Calibre's trash and notes, are skipped.
+    pub fn book_directories(&self) -> Result<Vec<String>> {
+        let subdirectories = |path: &Path| -> Result<Vec<(String, PathBuf)>> {
+            let mut subdirectories = Vec::new();
+
+            for entry in ::std::fs::read_dir(path)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+
+                if entry.file_type()?.is_dir() && !name.starts_with('.') {
+                    subdirectories.push((name, entry.path()));
+                }
+            }
+
+            Ok(subdirectories)
+        };
+
+        let mut book_directories = Vec::new();
+
+        for (author, author_path) in subdirectories(&self.path)? {
+            for (title, _) in subdirectories(&author_path)? {
+                book_directories.push(format!("{author}/{title}"));
+            }
+        }
+
+        book_directories.sort();
+
+        Ok(book_directories)
+    }
+
+    fn current_version(&self) -> Result<Option<SourceVersion>> {
+        Ok(SourceVersion::from_metadata(&::std::fs::metadata(
+            self.database(),
+        )?))
+    }
+
+    /// Whether the database changed since it was opened. An immutable
+    /// connection does not notice changes, so the library has to be reopened.
+    #[cfg_attr(feature = "tracing", tracing::instrument)]
+    pub async fn is_stale(&self) -> Result<bool> {
+        Ok(match (&self.source_version, self.current_version()?) {
+            (Some(opened), Some(current)) => *opened != current,
+            _ => false,
+        })
+    }
+
+    /// Open the database again into a new library, leaving this one untouched
+    /// so it can keep serving until the new one replaces it.
+    ///
+    /// Returns `None` when the database did not change since it was opened.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(configurer)))]
+    pub async fn refreshed<C>(&self, configurer: C) -> Result<Option<Self>>
+    where
+        C: FnMut(&mut sea_orm::ConnectOptions),
+    {
+        if !self.is_stale().await? {
+            return Ok(None);
+        }
+
+        let mut library = Self::new(self.path.clone())?;
+        library.index_dir = self.index_dir.clone();
+        library.connect_with_config(configurer).await?;
+
+        Ok(Some(library))
+    }
+}
+
+impl CalibreLibrary for LocalLibrary {
+    type ResourcePath = PathBuf;
+
+    #[inline]
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    fn database(&self) -> PathBuf {
+        self.path.join("metadata.db")
+    }
+
+    fn database_path(&self) -> Option<Self::ResourcePath> {
+        Some(self.database())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(configurer)))]
+    async fn connect_with_config<C>(
+        &mut self,
+        mut configurer: C,
+    ) -> Result<&sea_orm::DatabaseConnection>
+    where
+        C: FnMut(&mut sea_orm::ConnectOptions),
+    {
+        let database = self.database();
+
+        if !database.is_file() {
+            return Err(Error::LocalLibrary(format!(
+                "No Calibre database found at {}",
+                database.display()
+            )));
+        }
+
+        self.source_version = self.current_version()?;
+
+        let mut opts = sea_orm::ConnectOptions::new(format!("sqlite://{}", database.display()));
+        configurer(&mut opts);
+
+        self.conn = Some(
+            connect_with_sidecar(&database, self.sidecar(), opts, DatabaseAccess::ReadOnly)
+                .await
+                .map_err(|err| Error::LocalLibrary(format!("Failed opening database: {err}")))?,
+        );
+
+        Schema::inspect(self.conn())
+            .await?
+            .check(self.conn())
+            .await?;
+
+        self.custom_columns = CustomColumns::load(self.conn()).await?;
+        SearchIndexUpdate::execute(self.conn(), &self.custom_columns).await?;
+
+        Ok(self.conn.as_ref().unwrap())
+    }
+
+    #[inline]
+    fn conn(&self) -> &sea_orm::DatabaseConnection {
+        self.conn.as_ref().unwrap()
+    }
+
+    #[inline]
+    fn custom_columns(&self) -> &CustomColumns {
+        &self.custom_columns
+    }
+
+    fn resource_path(&self, resource_name: &str) -> Result<Self::ResourcePath> {
+        Ok(self.path.join(resource_name))
+    }
+
+    #[inline]
+    fn flat_book_resource_path(
+        &self,
+        flat_book: &flat_books::Model,
+        resource_name: &str,
+    ) -> Result<Self::ResourcePath> {
+        Ok(self.book_resource_path(&flat_book.path, resource_name))
+    }
+}
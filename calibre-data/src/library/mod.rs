@@ -1,5 +1,38 @@
 mod calibre_library;
 pub use calibre_library::*;
 
+mod library_backend;
+pub use library_backend::*;
+
+mod local_library;
+pub use local_library::*;
+
 mod remote_library;
 pub use remote_library::*;
+
+mod source_version;
+pub use source_version::*;
+
+mod search_index_update;
+pub use search_index_update::*;
+
+mod sidecar;
+pub use sidecar::*;
+
+mod resource_cache;
+pub use resource_cache::*;
+
+mod fetch;
+pub use fetch::*;
+
+mod credentials;
+pub use credentials::*;
+
+mod url_signer;
+pub use url_signer::*;
+
+mod s3_presigner;
+pub use s3_presigner::*;
+
+mod s3_library;
+pub use s3_library::*;
@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{Error, Result},
+    library::{RemoteLibrary, ResolvedResource, ResourceRequest, SourceVersion},
+};
+
+/// Settings of a `ResourceCache`.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub struct ResourceCacheOptions {
+    /// Directory holding the cached files
+    pub directory: PathBuf,
+    /// Total size of the cached files, in bytes
+    pub max_size: u64,
+    /// Time an entry is served before it is revalidated with the source
+    pub max_age: Duration,
+}
+
+/// Resource stored in the cache, persisted as json next to its file.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    /// Source url of the resource, holding the book path
+    key: String,
+    file_name: String,
+    size: u64,
+    /// Validators sent by the source, used to revalidate the entry
+    version: Option<SourceVersion>,
+    validated_at: SystemTime,
+    #[serde(skip)]
+    last_used: u64,
+}
+
+#[derive(::std::fmt::Debug, ::std::default::Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    size: u64,
+    tick: u64,
+}
+
+/// On-disk cache of book files and covers fetched from remote libraries.
+///
+/// Entries are keyed by their source url, made of the library source, the
+/// book path and the resource name, and are served as local files. Entries
+/// older than `max_age` are revalidated with the validators of the source
+/// before being served again, and the least recently used entries are
+/// evicted once the cache grows beyond `max_size`.
+///
+/// One cache can be shared by every library, and survives restarts.
+#[derive(::std::fmt::Debug)]
+pub struct ResourceCache {
+    options: ResourceCacheOptions,
+    state: Mutex<CacheState>,
+}
+
+impl ResourceCache {
+    /// Open the cache in `options.directory`, picking up entries stored by
+    /// a previous run and discarding incomplete ones.
+    pub fn open(options: ResourceCacheOptions) -> Result<Self> {
+        ::std::fs::create_dir_all(&options.directory)?;
+
+        let cache = Self {
+            options,
+            state: Mutex::new(CacheState::default()),
+        };
+
+        let mut entries = Vec::new();
+
+        for dir_entry in ::std::fs::read_dir(&cache.options.directory)? {
+            let path = dir_entry?.path();
+
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let entry = ::std::fs::read(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice::<CacheEntry>(&json).ok())
+                    .filter(|entry| {
+                        ::std::fs::metadata(cache.entry_path(entry))
+                            .is_ok_and(|metadata| metadata.len() == entry.size)
+                    });
+
+                match entry {
+                    Some(entry) => entries.push(entry),
+                    None => {
+                        let _ = ::std::fs::remove_file(&path);
+                        let _ = ::std::fs::remove_dir_all(path.with_extension(""));
+                    }
+                }
+            } else if path.is_dir() && !path.with_extension("json").exists() {
+                let _ = ::std::fs::remove_dir_all(&path);
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.validated_at);
+
+        let evicted = {
+            let mut state = cache.state.lock().unwrap();
+
+            for entry in entries {
+                state.tick += 1;
+                state.size += entry.size;
+
+                let entry = CacheEntry {
+                    last_used: state.tick,
+                    ..entry
+                };
+                state.entries.insert(entry.key.clone(), entry);
+            }
+
+            cache.evict(&mut state, None)
+        };
+
+        cache.remove_files(evicted);
+
+        Ok(cache)
+    }
+
+    /// Total size of the cached files, in bytes.
+    pub fn size(&self) -> u64 {
+        self.state.lock().unwrap().size
+    }
+
+    /// Number of cached resources.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resolve `resource` of `library` to its cached file, fetching it when
+    /// missing and revalidating it when expired.
+    ///
+    /// Expired entries keep being served when the source cannot be reached,
+    /// and resources larger than the whole cache are streamed instead.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, library)))]
+    pub async fn resolve(
+        &self,
+        library: &RemoteLibrary,
+        resource: &url::Url,
+    ) -> Result<ResolvedResource> {
+        let key = resource.as_str();
+        let cached = self.lookup(key);
+
+        if let Some(entry) = &cached {
+            if entry.validated_at.elapsed().unwrap_or_default() < self.options.max_age {
+                return Ok(ResolvedResource::Path(self.entry_path(entry)));
+            }
+        }
+
+        let request = cached
+            .as_ref()
+            .and_then(|entry| entry.version.as_ref())
+            .map(|version| ResourceRequest {
+                if_none_match: version.etag.clone(),
+                if_modified_since: version.modified.clone(),
+                ..ResourceRequest::default()
+            })
+            .unwrap_or_default();
+
+        let response = match (library.get_resource(resource, &request).await, &cached) {
+            (Ok(response), _) => response,
+
+            (Err(_err), Some(entry)) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Serving expired cache entry, revalidation failed: {_err}");
+
+                return Ok(ResolvedResource::Path(self.entry_path(entry)));
+            }
+
+            (Err(err), None) => return Err(err),
+        };
+
+        match (response.status(), cached) {
+            (reqwest::StatusCode::NOT_MODIFIED, Some(entry)) => {
+                let entry = self.revalidated(entry)?;
+                Ok(ResolvedResource::Path(self.entry_path(&entry)))
+            }
+
+            _ if response
+                .content_length()
+                .is_some_and(|length| length > self.options.max_size) =>
+            {
+                Ok(ResolvedResource::Stream(response.into()))
+            }
+
+            _ => {
+                let entry = self.store(key, resource, response).await?;
+                Ok(ResolvedResource::Path(self.entry_path(&entry)))
+            }
+        }
+    }
+
+    /// Entry of `key` when cached, marking it as used.
+    fn lookup(&self, key: &str) -> Option<CacheEntry> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        let entry = state.entries.get_mut(key)?;
+        entry.last_used = tick;
+        let entry = entry.clone();
+
+        // Files removed behind our back are fetched again.
+        if !self.entry_path(&entry).is_file() {
+            state.size -= entry.size;
+            state.entries.remove(key);
+            return None;
+        }
+
+        Some(entry)
+    }
+
+    /// Write the body of `response` to the cache as the entry of `key`.
+    async fn store(
+        &self,
+        key: &str,
+        resource: &url::Url,
+        response: reqwest::Response,
+    ) -> Result<CacheEntry> {
+        let hash = entry_hash(key);
+        let directory = self.options.directory.join(&hash);
+        ::std::fs::create_dir_all(&directory)?;
+
+        let version = SourceVersion::from_headers(response.headers());
+
+        let mut partial = tempfile::Builder::new()
+            .prefix(".partial-")
+            .tempfile_in(&directory)?;
+        let mut size = 0;
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            partial.write_all(&chunk)?;
+        }
+
+        let entry = CacheEntry {
+            key: key.to_string(),
+            file_name: file_name(resource),
+            size,
+            version,
+            validated_at: SystemTime::now(),
+            last_used: 0,
+        };
+
+        partial
+            .persist(self.entry_path(&entry))
+            .map_err(|err| err.error)?;
+        self.write_entry(&entry)?;
+
+        let (entry, evicted) = {
+            let mut state = self.state.lock().unwrap();
+            state.tick += 1;
+
+            let entry = CacheEntry {
+                last_used: state.tick,
+                ..entry
+            };
+
+            state.size += entry.size;
+            if let Some(replaced) = state.entries.insert(key.to_string(), entry.clone()) {
+                state.size -= replaced.size;
+            }
+
+            let evicted = self.evict(&mut state, Some(key));
+            (entry, evicted)
+        };
+
+        self.remove_files(evicted);
+
+        Ok(entry)
+    }
+
+    /// Mark `entry` as valid again after the source confirmed it.
+    fn revalidated(&self, entry: CacheEntry) -> Result<CacheEntry> {
+        let entry = CacheEntry {
+            validated_at: SystemTime::now(),
+            ..entry
+        };
+
+        if let Some(cached) = self.state.lock().unwrap().entries.get_mut(&entry.key) {
+            cached.validated_at = entry.validated_at;
+        }
+
+        self.write_entry(&entry)?;
+
+        Ok(entry)
+    }
+
+    /// Drop least recently used entries until the cache fits `max_size`,
+    /// keeping `keep` which is about to be served. Returns the hashes of the
+    /// dropped entries, whose files are removed once the lock is released.
+    fn evict(&self, state: &mut CacheState, keep: Option<&str>) -> Vec<String> {
+        let mut evicted = Vec::new();
+
+        while state.size > self.options.max_size {
+            let Some(key) = state
+                .entries
+                .values()
+                .filter(|entry| Some(entry.key.as_str()) != keep)
+                .min_by_key(|entry| entry.last_used)
+                .map(|entry| entry.key.clone())
+            else {
+                break;
+            };
+
+            if let Some(entry) = state.entries.remove(&key) {
+                state.size -= entry.size;
+                evicted.push(entry_hash(&key));
+            }
+        }
+
+        evicted
+    }
+
+    fn remove_files(&self, hashes: Vec<String>) {
+        for hash in hashes {
+            let directory = self.options.directory.join(&hash);
+            let _ = ::std::fs::remove_file(directory.with_extension("json"));
+            let _ = ::std::fs::remove_dir_all(directory);
+        }
+    }
+
+    fn write_entry(&self, entry: &CacheEntry) -> Result<()> {
+        let json = serde_json::to_vec(entry)
+            .map_err(|err| Error::ResourceCache(format!("Failed serializing entry: {err}")))?;
+
+        ::std::fs::write(
+            self.options
+                .directory
+                .join(entry_hash(&entry.key))
+                .with_extension("json"),
+            json,
+        )?;
+
+        Ok(())
+    }
+
+    /// Cached file of `entry`, named like the resource so it is served with
+    /// the right content type and download name.
+    fn entry_path(&self, entry: &CacheEntry) -> PathBuf {
+        self.options
+            .directory
+            .join(entry_hash(&entry.key))
+            .join(&entry.file_name)
+    }
+}
+
+fn entry_hash(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Last path segment of `resource`, decoded.
+fn file_name(resource: &url::Url) -> String {
+    resource
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|segment| percent_encoding::percent_decode_str(segment).decode_utf8_lossy())
+        .map(|name| name.replace(['/', '\\'], "_"))
+        .filter(|name| !name.is_empty() && !name.starts_with('.'))
+        .unwrap_or_else(|| "resource".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cache(directory: &::std::path::Path, max_size: u64) -> ResourceCache {
+        ResourceCache::open(ResourceCacheOptions {
+            directory: directory.to_path_buf(),
+            max_size,
+            max_age: Duration::from_secs(60),
+        })
+        .unwrap()
+    }
+
+    fn insert(cache: &ResourceCache, key: &str, size: u64) {
+        let entry = CacheEntry {
+            key: key.to_string(),
+            file_name: "cover.jpg".to_string(),
+            size,
+            version: None,
+            validated_at: SystemTime::now(),
+            last_used: 0,
+        };
+
+        let path = cache.entry_path(&entry);
+        ::std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        ::std::fs::write(path, vec![0; size as usize]).unwrap();
+        cache.write_entry(&entry).unwrap();
+
+        let mut state = cache.state.lock().unwrap();
+        state.tick += 1;
+        state.size += size;
+        let entry = CacheEntry {
+            last_used: state.tick,
+            ..entry
+        };
+        state.entries.insert(key.to_string(), entry);
+        let evicted = cache.evict(&mut state, Some(key));
+        drop(state);
+        cache.remove_files(evicted);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let cache = cache(directory.path(), 10);
+
+        insert(&cache, "a", 4);
+        insert(&cache, "b", 4);
+        assert!(cache.lookup("a").is_some());
+        insert(&cache, "c", 4);
+
+        assert!(cache.lookup("a").is_some());
+        assert!(cache.lookup("b").is_none());
+        assert!(cache.lookup("c").is_some());
+        assert_eq!(cache.size(), 8);
+    }
+
+    #[test]
+    fn reopens_stored_entries() {
+        let directory = tempfile::TempDir::new().unwrap();
+        insert(&cache(directory.path(), 10), "a", 4);
+        ::std::fs::create_dir(directory.path().join("partial")).unwrap();
+
+        let cache = cache(directory.path(), 10);
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 4);
+        assert!(!directory.path().join("partial").exists());
+    }
+
+    #[test]
+    fn file_name_is_last_segment() {
+        let url = url::Url::parse("https://host/Le%20Guin/A%20Wizard/cover.jpg").unwrap();
+        assert_eq!(file_name(&url), "cover.jpg");
+
+        let url = url::Url::parse("https://host/Le%20Guin/A%20Wizard/My%20Book.epub").unwrap();
+        assert_eq!(file_name(&url), "My Book.epub");
+    }
+}
@@ -0,0 +1,395 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use crate::{
+    custom_columns::CustomColumns,
+    entities::flat_books,
+    error::{Error, Result},
+    library::{
+        CalibreLibrary, FetchOptions, RemoteLibrary, ResourceCache, S3Credentials, S3Presigner,
+    },
+};
+
+/// How book files and covers of an S3 library reach the client.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::default::Default,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+)]
+pub enum S3Serving {
+    /// Redirect to a presigned url of the object
+    #[default]
+    Presign,
+    /// Stream the object through anserno
+    Stream,
+}
+
+/// Connection settings of an S3 compatible service.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub struct S3Options {
+    /// Service endpoint, AWS for `region` when unset
+    pub endpoint: Option<url::Url>,
+    pub region: String,
+    /// Access keys, anonymous access to a public bucket when unset
+    pub credentials: Option<S3Credentials>,
+    /// Address the bucket as the first path segment instead of a subdomain,
+    /// as most self hosted services expect
+    pub path_style: bool,
+    pub serving: S3Serving,
+    /// Validity of presigned urls
+    pub presign_expiry: Duration,
+}
+
+impl ::std::default::Default for S3Options {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            region: "us-east-1".to_string(),
+            credentials: None,
+            path_style: false,
+            serving: S3Serving::default(),
+            presign_expiry: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Calibre library stored in an S3 compatible bucket, addressed by an
+/// `s3://bucket/prefix` url.
+///
+/// The library is fetched like a `RemoteLibrary` from the service endpoint,
+/// with every request presigned when credentials are configured.
+#[derive(::std::fmt::Debug, ::std::clone::Clone)]
+pub struct S3Library {
+    source: url::Url,
+    inner: RemoteLibrary,
+    presigner: Option<Arc<S3Presigner>>,
+    serving: S3Serving,
+}
+
+impl S3Library {
+    /// Create a new S3 library from an `s3://bucket/prefix` url.
+    pub fn new(source: url::Url, options: S3Options) -> Result<Self> {
+        let bucket = source
+            .host_str()
+            .filter(|bucket| !bucket.is_empty())
+            .ok_or_else(|| Error::RemoteLibrary(format!("Missing bucket in {source}")))?;
+
+        let endpoint = match &options.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => url::Url::parse(&format!("https://s3.{}.amazonaws.com", options.region))?,
+        };
+
+        let mut location = endpoint.clone();
+
+        if !options.path_style {
+            let host = endpoint.host_str().ok_or_else(|| {
+                Error::RemoteLibrary(format!("Missing host in S3 endpoint {endpoint}"))
+            })?;
+            location.set_host(Some(&format!("{bucket}.{host}")))?;
+        }
+
+        {
+            let mut segments = location
+                .path_segments_mut()
+                .map_err(|_| Error::RemoteLibrary(format!("Invalid S3 endpoint {endpoint}")))?;
+            segments.pop_if_empty();
+
+            if options.path_style {
+                segments.push(bucket);
+            }
+
+            segments.extend(
+                source
+                    .path_segments()
+                    .into_iter()
+                    .flatten()
+                    .filter(|segment| !segment.is_empty()),
+            );
+        }
+
+        let presigner = options.credentials.map(|credentials| {
+            Arc::new(S3Presigner::new(
+                credentials,
+                options.region,
+                options.presign_expiry,
+            ))
+        });
+
+        let inner = match &presigner {
+            Some(presigner) => RemoteLibrary::new(location)?.with_url_signer(presigner.clone()),
+            None => RemoteLibrary::new(location)?,
+        };
+
+        Ok(Self {
+            source,
+            inner,
+            presigner,
+            serving: options.serving,
+        })
+    }
+
+    /// Set the limits used when fetching the database.
+    pub fn with_fetch_options(self, fetch_options: FetchOptions) -> Self {
+        Self {
+            inner: self.inner.with_fetch_options(fetch_options),
+            ..self
+        }
+    }
+
+    /// Serve objects from `resource_cache` rather than presigning or
+    /// streaming each request.
+    pub fn with_resource_cache(self, resource_cache: Arc<ResourceCache>) -> Self {
+        Self {
+            inner: self.inner.with_resource_cache(resource_cache),
+            ..self
+        }
+    }
+
+    /// Keep the sidecar database in `index_dir`, see
+    /// [`RemoteLibrary::with_index_dir`].
+    pub fn with_index_dir(self, index_dir: impl Into<::std::path::PathBuf>) -> Self {
+        Self {
+            inner: self.inner.with_index_dir(index_dir),
+            ..self
+        }
+    }
+
+    /// The `s3://` url of the library.
+    #[inline]
+    pub fn source(&self) -> &url::Url {
+        &self.source
+    }
+
+    #[inline]
+    pub fn serving(&self) -> S3Serving {
+        self.serving
+    }
+
+    /// Library fetched from the service endpoint.
+    #[inline]
+    pub fn remote_library(&self) -> &RemoteLibrary {
+        &self.inner
+    }
+
+    /// Url of an object which clients can fetch themselves, presigned when
+    /// the bucket is private.
+    pub fn presigned(&self, url: &url::Url) -> Result<url::Url> {
+        self.inner.signed(reqwest::Method::GET, url)
+    }
+
+    /// Whether requests are presigned.
+    #[inline]
+    pub fn is_private(&self) -> bool {
+        self.presigner.is_some()
+    }
+
+    /// Fetch the library again when it changed, see
+    /// [`RemoteLibrary::refreshed`].
+    pub async fn refreshed<C>(&self, configurer: C) -> Result<Option<Self>>
+    where
+        C: FnMut(&mut sea_orm::ConnectOptions),
+    {
+        Ok(self.inner.refreshed(configurer).await?.map(|inner| Self {
+            inner,
+            ..self.clone()
+        }))
+    }
+}
+
+impl CalibreLibrary for S3Library {
+    type ResourcePath = url::Url;
+
+    #[inline]
+    fn path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    #[inline]
+    fn database(&self) -> std::path::PathBuf {
+        self.inner.database()
+    }
+
+    fn database_path(&self) -> Option<Self::ResourcePath> {
+        self.inner.database_path()
+    }
+
+    async fn connect_with_config<C>(
+        &mut self,
+        configurer: C,
+    ) -> Result<&sea_orm::DatabaseConnection>
+    where
+        C: FnMut(&mut sea_orm::ConnectOptions),
+    {
+        self.inner.connect_with_config(configurer).await
+    }
+
+    #[inline]
+    fn conn(&self) -> &sea_orm::DatabaseConnection {
+        self.inner.conn()
+    }
+
+    #[inline]
+    fn custom_columns(&self) -> &CustomColumns {
+        self.inner.custom_columns()
+    }
+
+    fn resource_path(&self, resource_name: &str) -> Result<Self::ResourcePath> {
+        self.inner.resource_path(resource_name)
+    }
+
+    #[inline]
+    fn flat_book_resource_path(
+        &self,
+        flat_book: &flat_books::Model,
+        resource_name: &str,
+    ) -> Result<Self::ResourcePath> {
+        self.inner.flat_book_resource_path(flat_book, resource_name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use sea_orm::EntityTrait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::library::{LibraryBackend, ResolvedResource, ResourceRequest};
+
+    /// Stand-in for an S3 compatible service, answering every request with
+    /// `body` and returning its endpoint and the request lines received.
+    async fn serve(body: Vec<u8>) -> (url::Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0; 1];
+                    if stream.read(&mut byte).await.unwrap() == 0 {
+                        break;
+                    }
+                    head.push(byte[0]);
+                }
+                received.lock().unwrap().push(
+                    String::from_utf8_lossy(&head)
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(&body).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        let endpoint = format!("http://{address}").parse().unwrap();
+
+        (endpoint, requests)
+    }
+
+    fn options(endpoint: &str) -> S3Options {
+        S3Options {
+            endpoint: Some(endpoint.parse().unwrap()),
+            credentials: Some(S3Credentials {
+                access_key_id: "minio".to_string(),
+                secret_access_key: "minio-secret".to_string(),
+                session_token: None,
+            }),
+            ..S3Options::default()
+        }
+    }
+
+    #[test]
+    fn addresses_bucket_by_host_or_path() {
+        let source = url::Url::parse("s3://books/calibre/fiction").unwrap();
+
+        let library = S3Library::new(source.clone(), options("http://localhost:9000")).unwrap();
+        assert_eq!(
+            library.remote_library().source().as_str(),
+            "http://books.localhost:9000/calibre/fiction"
+        );
+
+        let library = S3Library::new(
+            source,
+            S3Options {
+                path_style: true,
+                ..options("http://localhost:9000/")
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            library.remote_library().source().as_str(),
+            "http://localhost:9000/books/calibre/fiction"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_and_serves_presigned_objects() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database = crate::testing::create_library(directory.path())
+            .await
+            .unwrap();
+        let (endpoint, requests) = serve(::std::fs::read(database).unwrap()).await;
+
+        let mut library = S3Library::new(
+            "s3://books/calibre".parse().unwrap(),
+            S3Options {
+                path_style: true,
+                ..options(endpoint.as_str())
+            },
+        )
+        .unwrap();
+        assert!(library.is_private());
+
+        library.connect_with_config(|_| {}).await.unwrap();
+
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(
+            request.starts_with("GET /books/calibre/metadata.db?X-Amz-Algorithm=AWS4-HMAC-SHA256&"),
+            "{request}"
+        );
+        assert!(request.contains("&X-Amz-Signature="), "{request}");
+
+        let flat_book = flat_books::Entity::find_by_id(1)
+            .one(CalibreLibrary::conn(&library))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let resource = library
+            .flat_book_resource(&flat_book, "cover.jpg", &ResourceRequest::default())
+            .await
+            .unwrap();
+
+        let ResolvedResource::Url(url) = resource else {
+            panic!("{resource:?} is not a presigned url");
+        };
+        assert_eq!(url.host_str(), endpoint.host_str());
+        assert_eq!(
+            url.path(),
+            "/books/calibre/Ursula%20K.%20Le%20Guin/A%20Wizard%20of%20Earthsea%20(1)/cover.jpg"
+        );
+        assert!(url.query_pairs().any(|(key, _)| key == "X-Amz-Signature"));
+
+        // Presigning hands out urls, without requesting the object.
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
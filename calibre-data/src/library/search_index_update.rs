@@ -0,0 +1,298 @@
+use sea_orm::{ConnectionTrait, Statement, TransactionTrait};
+
+use crate::{
+    custom_columns::CustomColumns,
+    error::Result,
+    queries::{
+        ClearSuggestIndex, CreateAttachedIndexedBooks, CreateAttachedSearchIndex,
+        CreateAttachedSearchIndexMeta, CreateAttachedSuggestIndex, DropAttachedIndexedBooks,
+        DropAttachedSearchIndex, DropAttachedSuggestIndex, PopulateSearchIndex,
+        PopulateSuggestIndex, PruneIndexedBooks, PruneSearchIndex, RecordIndexedBooks, StaticQuery,
+    },
+    search::strip_markup,
+};
+
+/// Layout version of the search index, bumped whenever its table or what is
+/// indexed in it changes so persisted indexes are rebuilt.
+pub const SEARCH_INDEX_VERSION: u32 = 4;
+
+/// Outcome of bringing the search index up to date with its library.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::default::Default,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+)]
+pub struct SearchIndexUpdate {
+    /// Whether the index was built from scratch
+    pub rebuilt: bool,
+    /// Books removed from the index, as they changed or were deleted
+    pub removed: u64,
+    /// Books added to the index, as they changed or are new
+    pub added: u64,
+}
+
+impl SearchIndexUpdate {
+    /// Bring the search index of the attached sidecar up to date with the
+    /// library of `conn`, in a single transaction.
+    ///
+    /// The index is rebuilt when it was built for another library, index
+    /// version or set of custom columns. Otherwise it is left untouched when
+    /// the number of books and their latest `last_modified` did not change
+    /// since the last update, and only the books whose `last_modified`
+    /// changed are indexed again. The suggest index, small in comparison, is
+    /// populated again whenever the search index changes.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn, custom_columns)))]
+    pub async fn execute<C>(conn: &C, custom_columns: &CustomColumns) -> Result<Self>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let txn = conn.begin().await?;
+
+        CreateAttachedSearchIndexMeta::execute(&txn).await?;
+
+        let key = index_key(&txn, custom_columns).await?;
+        let rebuilt = meta(&txn, "key").await?.as_deref() != Some(key.as_str());
+
+        if rebuilt {
+            DropAttachedSearchIndex::execute(&txn).await?;
+            DropAttachedIndexedBooks::execute(&txn).await?;
+            DropAttachedSuggestIndex::execute(&txn).await?;
+        }
+
+        CreateAttachedSearchIndex::execute(&txn).await?;
+        CreateAttachedIndexedBooks::execute(&txn).await?;
+        CreateAttachedSuggestIndex::execute(&txn).await?;
+
+        let fingerprint = query_string(
+            &txn,
+            r#"SELECT COUNT(*) || ':' || COALESCE(MAX("last_modified"), '') FROM "books""#,
+        )
+        .await?
+        .unwrap_or_default();
+
+        if !rebuilt && meta(&txn, "fingerprint").await?.as_deref() == Some(fingerprint.as_str()) {
+            txn.commit().await?;
+            return Ok(Self::default());
+        }
+
+        let removed = PruneSearchIndex::execute(&txn).await?.rows_affected();
+        PruneIndexedBooks::execute(&txn).await?;
+
+        let added = PopulateSearchIndex::execute(&txn).await?.rows_affected();
+        strip_descriptions(&txn).await?;
+        custom_columns.populate_search_index(&txn).await?;
+        RecordIndexedBooks::execute(&txn).await?;
+
+        ClearSuggestIndex::execute(&txn).await?;
+        PopulateSuggestIndex::execute(&txn).await?;
+
+        set_meta(&txn, "key", &key).await?;
+        set_meta(&txn, "fingerprint", &fingerprint).await?;
+
+        txn.commit().await?;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "Search index updated, rebuilt: {rebuilt}, removed: {removed}, added: {added}"
+        );
+
+        Ok(Self {
+            rebuilt,
+            removed,
+            added,
+        })
+    }
+}
+
+/// Identity of what the index holds: its version, the library's uuid and
+/// the searchable custom columns.
+async fn index_key<C: ConnectionTrait>(conn: &C, custom_columns: &CustomColumns) -> Result<String> {
+    // Older libraries have no `library_id` table.
+    let has_library_id = query_string(
+        conn,
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'library_id'",
+    )
+    .await?
+    .is_some();
+
+    let library_id = if has_library_id {
+        query_string(conn, r#"SELECT "uuid" FROM "library_id""#).await?
+    } else {
+        None
+    };
+
+    let custom_labels = custom_columns
+        .iter()
+        .filter(|column| column.datatype.is_searchable())
+        .map(|column| column.label.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(format!(
+        "{SEARCH_INDEX_VERSION}:{}:{custom_labels}",
+        library_id.unwrap_or_default()
+    ))
+}
+
+/// Replace the html descriptions of the books not indexed yet by their text,
+/// so neither searches nor snippets see the markup.
+async fn strip_descriptions<C: ConnectionTrait>(conn: &C) -> Result<()> {
+    let backend = conn.get_database_backend();
+
+    let descriptions = conn
+        .query_all(Statement::from_string(
+            backend,
+            indoc::indoc! {r#"
+                SELECT "rowid", "description" FROM "anserno_search_index"
+                WHERE ("description" LIKE '%<%' OR "description" LIKE '%&%')
+                AND "rowid" NOT IN (SELECT "id" FROM "anserno_indexed_books")
+            "#},
+        ))
+        .await?;
+
+    for row in descriptions {
+        let book_id = row.try_get_by_index::<i32>(0)?;
+        let description = row.try_get_by_index::<String>(1)?;
+
+        conn.execute(Statement::from_sql_and_values(
+            backend,
+            r#"UPDATE "anserno_search_index" SET "description" = ? WHERE "rowid" = ?"#,
+            [strip_markup(&description).into(), book_id.into()],
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn query_string<C: ConnectionTrait>(conn: &C, sql: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_one(Statement::from_string(conn.get_database_backend(), sql))
+        .await?
+        .map(|row| row.try_get_by_index::<String>(0))
+        .transpose()?)
+}
+
+async fn meta<C: ConnectionTrait>(conn: &C, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_one(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            r#"SELECT "value" FROM "anserno_search_index_meta" WHERE "key" = ?"#,
+            [key.into()],
+        ))
+        .await?
+        .map(|row| row.try_get_by_index::<String>(0))
+        .transpose()?)
+}
+
+async fn set_meta<C: ConnectionTrait>(conn: &C, key: &str, value: &str) -> Result<()> {
+    conn.execute(Statement::from_sql_and_values(
+        conn.get_database_backend(),
+        indoc::indoc! {r#"
+            INSERT INTO "anserno_search_index_meta" ("key", "value") VALUES (?, ?)
+            ON CONFLICT ("key") DO UPDATE SET "value" = "excluded"."value"
+        "#},
+        [key.into(), value.into()],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        library::{connect_with_sidecar, DatabaseAccess},
+        testing,
+    };
+
+    /// Update the index in `sidecar` from a fresh connection to `database`,
+    /// as when a changed library is connected again.
+    async fn update(database: &Path, sidecar: &Path) -> SearchIndexUpdate {
+        let conn = connect_with_sidecar(
+            database,
+            sidecar.to_path_buf(),
+            sea_orm::ConnectOptions::new(""),
+            DatabaseAccess::Immutable,
+        )
+        .await
+        .unwrap();
+
+        let custom_columns = CustomColumns::load(&conn).await.unwrap();
+        let update = SearchIndexUpdate::execute(&conn, &custom_columns)
+            .await
+            .unwrap();
+
+        conn.close().await.unwrap();
+
+        update
+    }
+
+    #[tokio::test]
+    async fn indexes_changed_books_only() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database = testing::create_library(directory.path()).await.unwrap();
+        let sidecar = directory.path().join("anserno.db");
+
+        let built = SearchIndexUpdate {
+            rebuilt: true,
+            removed: 0,
+            added: 3,
+        };
+        assert_eq!(update(&database, &sidecar).await, built);
+
+        assert_eq!(
+            update(&database, &sidecar).await,
+            SearchIndexUpdate::default()
+        );
+
+        testing::execute_sql(
+            &database,
+            "UPDATE books SET last_modified = '2025-01-01 00:00:00+00:00' WHERE id = 2;",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            update(&database, &sidecar).await,
+            SearchIndexUpdate {
+                rebuilt: false,
+                removed: 1,
+                added: 1,
+            }
+        );
+
+        testing::execute_sql(&database, "DELETE FROM books WHERE id = 3;")
+            .await
+            .unwrap();
+        assert_eq!(
+            update(&database, &sidecar).await,
+            SearchIndexUpdate {
+                rebuilt: false,
+                removed: 1,
+                added: 0,
+            }
+        );
+
+        // Searchable custom columns are part of the index.
+        testing::execute_sql(
+            &database,
+            "UPDATE custom_columns SET mark_for_delete = 1 WHERE label = 'shelf';",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            update(&database, &sidecar).await,
+            SearchIndexUpdate {
+                rebuilt: true,
+                removed: 0,
+                added: 2,
+            }
+        );
+    }
+}
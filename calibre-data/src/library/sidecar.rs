@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use sea_orm::sqlx::{self, ConnectOptions as _};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    queries::{CreateTempFlatBooksView, StaticQuery},
+    schema::LINK_COLUMN_TABLES,
+};
+
+/// Schema name of the attached anserno database.
+pub const LIBRARY_SIDECAR_SCHEMA: &str = "anserno";
+
+/// Characters escaped in the path of a database uri.
+const URI_PATH_ESCAPED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// How the Calibre database of a library is opened.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+)]
+pub enum DatabaseAccess {
+    /// Read-only with the usual locking, for databases other processes such
+    /// as Calibre keep writing to
+    ReadOnly,
+    /// Immutable, without any locking or change detection, for private
+    /// copies nothing else writes to
+    Immutable,
+}
+
+/// Path of the sidecar database of the library at `source`.
+///
+/// Sidecars are kept in `index_dir` when set, named after the source so a
+/// library finds its own sidecar again after a restart, and in the library's
+/// temporary directory otherwise.
+pub fn sidecar_path(index_dir: Option<&Path>, tempdir: &Path, source: &str) -> PathBuf {
+    match index_dir {
+        Some(index_dir) => index_dir.join(format!(
+            "{}.db",
+            &hex::encode(Sha256::digest(source.as_bytes()))[..32]
+        )),
+        None => tempdir.join("anserno.db"),
+    }
+}
+
+/// Open the Calibre `database` read-only as set by `access`, with the
+/// `sidecar` database attached to every connection, creating it if missing,
+/// along with a temporary flat_books view, and temporary views adding the
+/// `link` columns older libraries lack.
+pub(crate) async fn connect_with_sidecar(
+    database: &Path,
+    sidecar: PathBuf,
+    mut opts: sea_orm::ConnectOptions,
+    access: DatabaseAccess,
+) -> ::std::result::Result<sea_orm::DatabaseConnection, sqlx::Error> {
+    let connect_options = sqlx::sqlite::SqliteConnectOptions::new();
+
+    let connect_options = match access {
+        DatabaseAccess::Immutable => connect_options.filename(database).immutable(true),
+        // Read-only connections would leave the attached sidecar read-only
+        // too, the `mode` of a uri filename only applies to its own database.
+        DatabaseAccess::ReadOnly => connect_options.filename(format!(
+            "file:{}?mode=ro",
+            percent_encoding::utf8_percent_encode(&database.to_string_lossy(), URI_PATH_ESCAPED)
+        )),
+    };
+
+    // Creating missing files only applies to the attached sidecar, the
+    // library database is never written to.
+    let mut connect_options = connect_options.create_if_missing(true).with_regexp();
+
+    connect_options = if opts.get_sqlx_logging() {
+        connect_options.log_statements(opts.get_sqlx_logging_level())
+    } else {
+        connect_options.disable_statement_logging()
+    };
+
+    if opts.get_max_connections().is_none() {
+        opts.max_connections(1);
+    }
+
+    let pool = opts
+        .sqlx_pool_options::<sqlx::Sqlite>()
+        .after_connect(move |conn, _| {
+            let sidecar = sidecar.clone();
+
+            Box::pin(async move {
+                sqlx::query(&format!(
+                    "ATTACH DATABASE ? AS \"{LIBRARY_SIDECAR_SCHEMA}\""
+                ))
+                .bind(sidecar.to_string_lossy().into_owned())
+                .execute(&mut *conn)
+                .await?;
+
+                for table in LINK_COLUMN_TABLES {
+                    let (columns, links): (i64, i64) = sqlx::query_as(
+                        "SELECT COUNT(*), COUNT(*) FILTER (WHERE name = 'link') \
+                         FROM pragma_table_info(?)",
+                    )
+                    .bind(table)
+                    .fetch_one(&mut *conn)
+                    .await?;
+
+                    if columns > 0 && links == 0 {
+                        sqlx::query(&format!(
+                            r#"CREATE TEMP VIEW IF NOT EXISTS "{table}" AS
+                               SELECT *, '' AS "link" FROM "main"."{table}""#
+                        ))
+                        .execute(&mut *conn)
+                        .await?;
+                    }
+                }
+
+                sqlx::query(CreateTempFlatBooksView::QUERY)
+                    .execute(&mut *conn)
+                    .await?;
+
+                Ok(())
+            })
+        })
+        .connect_with(connect_options)
+        .await?;
+
+    Ok(sea_orm::SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::{ConnectionTrait, EntityTrait};
+
+    use super::*;
+    use crate::{
+        entities::{books, tags},
+        testing,
+    };
+
+    #[tokio::test]
+    async fn fills_in_link_columns() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database = testing::create_library(directory.path()).await.unwrap();
+        testing::execute_sql(
+            &database,
+            "PRAGMA user_version = 24; ALTER TABLE tags DROP COLUMN link;",
+        )
+        .await
+        .unwrap();
+
+        let conn = connect_with_sidecar(
+            &database,
+            directory.path().join("anserno.db"),
+            sea_orm::ConnectOptions::new(""),
+            DatabaseAccess::Immutable,
+        )
+        .await
+        .unwrap();
+
+        let tags = tags::Entity::find().all(&conn).await.unwrap();
+
+        assert_eq!(tags.len(), 3);
+        assert!(tags.iter().all(|tag| tag.link.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn read_only_databases_follow_changes() {
+        // Paths are escaped in the uri of read-only databases.
+        let directory = tempfile::Builder::new()
+            .prefix("Calibre Library?#%")
+            .tempdir()
+            .unwrap();
+        let database = testing::create_library(directory.path()).await.unwrap();
+
+        let conn = connect_with_sidecar(
+            &database,
+            directory.path().join("anserno.db"),
+            sea_orm::ConnectOptions::new(""),
+            DatabaseAccess::ReadOnly,
+        )
+        .await
+        .unwrap();
+
+        assert!(conn
+            .execute_unprepared(r#"UPDATE "main"."books" SET "title" = 'Changed'"#)
+            .await
+            .is_err());
+        conn.execute_unprepared(r#"CREATE TABLE "anserno"."scratch" ("value" TEXT)"#)
+            .await
+            .unwrap();
+
+        testing::execute_sql(
+            &database,
+            "UPDATE books SET title = 'A Wizard of Earthsea, Revised' WHERE id = 1;",
+        )
+        .await
+        .unwrap();
+
+        let book = books::Entity::find_by_id(1)
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(book.title, "A Wizard of Earthsea, Revised");
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::UNIX_EPOCH;
+
+use crate::{
+    error::{Error, Result},
+    library::FetchOptions,
+};
+
+/// Fingerprint of a source database, compared to detect changes.
+///
+/// Local files are identified by modification time and size, http(s)
+/// sources by their `etag`, `last-modified` and `content-length` headers.
+#[derive(
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct SourceVersion {
+    pub modified: Option<String>,
+    pub etag: Option<String>,
+    pub length: Option<u64>,
+}
+
+impl SourceVersion {
+    /// Read the version of the database at `source`, `None` when the source
+    /// exposes nothing to compare. Http(s) sources are queried with `HEAD`.
+    pub async fn fetch(source: &url::Url, options: &FetchOptions) -> Result<Option<Self>> {
+        match source.origin() {
+            url::Origin::Opaque(_) => {
+                let path = source.to_file_path().map_err(|_| {
+                    Error::RemoteLibrary(format!("Failed rendering database source: {source:?}"))
+                })?;
+
+                Ok(Self::from_metadata(&::std::fs::metadata(path)?))
+            }
+
+            url::Origin::Tuple(_, _, _) => {
+                let response = options
+                    .client()?
+                    .head(source.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+
+                Ok(Self::from_headers(response.headers()))
+            }
+        }
+    }
+
+    pub fn from_metadata(metadata: &::std::fs::Metadata) -> Option<Self> {
+        Self {
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| modified.as_nanos().to_string()),
+            etag: None,
+            length: Some(metadata.len()),
+        }
+        .into_option()
+    }
+
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let header = |name: reqwest::header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            modified: header(reqwest::header::LAST_MODIFIED),
+            etag: header(reqwest::header::ETAG),
+            length: header(reqwest::header::CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+        }
+        .into_option()
+    }
+
+    /// Validator for `If-Range`, the entity tag when present.
+    pub fn validator(&self) -> Option<&str> {
+        self.etag.as_deref().or(self.modified.as_deref())
+    }
+
+    fn into_option(self) -> Option<Self> {
+        Some(self).filter(|version| {
+            version.modified.is_some() || version.etag.is_some() || version.length.is_some()
+        })
+    }
+}
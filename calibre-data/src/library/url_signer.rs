@@ -0,0 +1,8 @@
+use crate::error::Result;
+
+/// Signs source urls so they can be requested without further credentials,
+/// as done for object storage presigned urls.
+pub trait UrlSigner: ::std::fmt::Debug + ::core::marker::Send + ::core::marker::Sync {
+    /// Signed version of `url` for a `method` request
+    fn sign(&self, method: &reqwest::Method, url: &url::Url) -> Result<url::Url>;
+}
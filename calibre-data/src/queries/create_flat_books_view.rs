@@ -1,75 +1,161 @@
 use crate::queries::StaticQuery;
 
+/// Statement creating the flat_books view, prefixed by `$create`.
+macro_rules! create_flat_books_view {
+    ($create:literal) => {
+        indoc::concatdoc! {$create, r#" anserno_flat_books (
+                   "id", "title", "sort", "path", "authors", "series", "series_index", "formats", "description", "tags", "publishers", "rating",
+                   "identifiers", "pubdate", "timestamp", "last_modified", "has_cover", "uuid"
+                ) AS
+                WITH
+                    author_json AS (
+                        SELECT
+                            books.id AS book_id,
+                            json_group_object(authors.id, authors.name) AS data
+                        FROM
+                            authors
+                        LEFT JOIN books_authors_link
+                            ON authors.id = books_authors_link.author
+                        LEFT JOIN books
+                            ON books.id = books_authors_link.book
+                        GROUP BY
+                            books.id
+                    ),
+                    series_json AS (
+                        SELECT
+                            books.id as book_id,
+                            json_group_object(series.id, series.name) AS data
+                        FROM
+                            series
+                        LEFT JOIN books_series_link
+                            ON series.id = books_series_link.series
+                        LEFT JOIN books
+                            ON books.id = books_series_link.book
+                        GROUP BY
+                            books.id
+                    ),
+                    format_json AS (
+                        SELECT
+                            books.id AS book_id,
+                            json_group_object(
+                                data.format,
+                                json_object('name', data.name, 'size', data.uncompressed_size)
+                            ) AS data
+                        FROM
+                            data
+                        LEFT JOIN books
+                            ON books.id = data.book
+                        GROUP BY
+                            books.id
+                    ),
+                    tag_json AS (
+                        SELECT
+                            books.id AS book_id,
+                            json_group_object(tags.id, tags.name) AS data
+                        FROM
+                            tags
+                        LEFT JOIN books_tags_link
+                            ON tags.id = books_tags_link.tag
+                        LEFT JOIN books
+                            ON books.id = books_tags_link.book
+                        GROUP BY
+                            books.id
+                    ),
+                    publisher_json AS (
+                        SELECT
+                            books.id AS book_id,
+                            json_group_object(publishers.id, publishers.name) AS data
+                        FROM
+                            publishers
+                        LEFT JOIN books_publishers_link
+                            ON publishers.id = books_publishers_link.publisher
+                        LEFT JOIN books
+                            ON books.id = books_publishers_link.book
+                        GROUP BY
+                            books.id
+                    ),
+                    identifier_json AS (
+                        SELECT
+                            identifiers.book AS book_id,
+                            json_group_object(identifiers.type, identifiers.val) AS data
+                        FROM
+                            identifiers
+                        GROUP BY
+                            identifiers.book
+                    ),
+                    rating_value AS (
+                        SELECT
+                            books_ratings_link.book AS book_id,
+                            MAX(ratings.rating) AS data
+                        FROM
+                            ratings
+                        LEFT JOIN books_ratings_link
+                            ON ratings.id = books_ratings_link.rating
+                        GROUP BY
+                            books_ratings_link.book
+                    )
+                SELECT
+                    "books"."id" AS "id",
+                    "books"."title" AS "title",
+                    "books"."sort" AS "sort",
+                    "books"."path" AS "path",
+                    COALESCE("author_json"."data", json('{}')) AS "authors",
+                    COALESCE("series_json"."data", json('{}')) AS "series",
+                    "books"."series_index" AS "series_index",
+                    COALESCE("format_json"."data", json('{}')) AS "formats",
+                    COALESCE("comments"."text", '') AS "description",
+                    COALESCE("tag_json"."data", json('{}')) AS "tags",
+                    COALESCE("publisher_json"."data", json('{}')) AS "publishers",
+                    "rating_value"."data" AS "rating",
+                    COALESCE("identifier_json"."data", json('{}')) AS "identifiers",
+                    CASE
+                        WHEN "books"."pubdate" < '0102' THEN NULL
+                        ELSE "books"."pubdate"
+                    END AS "pubdate",
+                    "books"."timestamp" AS "timestamp",
+                    "books"."last_modified" AS "last_modified",
+                    "books"."has_cover" AS "has_cover",
+                    "books"."uuid" AS "uuid"
+                FROM
+                    books
+                LEFT JOIN
+                    author_json ON books.id = author_json.book_id
+                LEFT JOIN
+                    series_json ON books.id = series_json.book_id
+                LEFT JOIN
+                    format_json ON books.id = format_json.book_id
+                LEFT JOIN
+                    tag_json ON books.id = tag_json.book_id
+                LEFT JOIN
+                    publisher_json ON books.id = publisher_json.book_id
+                LEFT JOIN
+                    identifier_json ON books.id = identifier_json.book_id
+                LEFT JOIN
+                    rating_value ON books.id = rating_value.book_id
+                LEFT JOIN
+                    comments ON books.id = comments.book
+        "#}
+    };
+}
+
 pub struct CreateFlatBooksView;
 
 /// Create the flat_books view.
 ///
 /// flat_books is a denomalized representation of books and supporting data to
-/// facilitate display to the user. It collects the `authors`, `series`, and
-/// `format` data into json maps, along with a few other fields as their
-/// string values.
+/// facilitate display to the user. It collects the `authors`, `series`,
+/// `format`, `tags`, `publishers` and `identifiers` data into json maps, along
+/// with a few other fields, such as the book's 0-10 `rating` and its dates, as
+/// their scalar values. Formats map to objects carrying the file `name` and its
+/// `size` in bytes. Calibre's undefined publication date reads as `NULL`.
 impl StaticQuery for CreateFlatBooksView {
-    const QUERY: &str = indoc::indoc! {r#"
-        CREATE VIEW IF NOT EXISTS anserno_flat_books (
-           "id", "title", "sort", "path", "authors", "series", "series_index", "formats", "description"
-        ) AS
-        WITH
-            author_json AS (
-                SELECT
-                    books.id AS book_id,
-                    json_group_object(authors.id, authors.name) AS data
-                FROM
-                    authors
-                LEFT JOIN books_authors_link
-                    ON authors.id = books_authors_link.author
-                LEFT JOIN books
-                    ON books.id = books_authors_link.book
-                GROUP BY
-                    books.id
-            ),
-            series_json AS (
-                SELECT
-                    books.id as book_id,
-                    json_group_object(series.id, series.name) AS data
-                FROM
-                    series
-                LEFT JOIN books_series_link
-                    ON series.id = books_series_link.series
-                LEFT JOIN books
-                    ON books.id = books_series_link.book
-                GROUP BY
-                    books.id
-            ),
-            format_json AS (
-                SELECT
-                    books.id AS book_id,
-                    json_group_object(data.format, data.name) AS data
-                FROM
-                    data
-                LEFT JOIN books
-                    ON books.id = data.book
-                GROUP BY
-                    books.id
-            )
-        SELECT
-            "books"."id" AS "id",
-            "books"."title" AS "title",
-            "books"."sort" AS "sort",
-            "books"."path" AS "path",
-            COALESCE("author_json"."data", json('{}')) AS "authors",
-            COALESCE("series_json"."data", json('{}')) AS "series",
-            "books"."series_index" AS "series_index",
-            COALESCE("format_json"."data", json('{}')) AS "formats",
-            COALESCE("comments"."text", '') AS "description"
-        FROM
-            books
-        LEFT JOIN
-            author_json ON books.id = author_json.book_id
-        LEFT JOIN
-            series_json ON books.id = series_json.book_id
-        LEFT JOIN
-            format_json ON books.id = format_json.book_id
-        LEFT JOIN
-            comments ON books.id = comments.book
-    "#};
+    const QUERY: &str = create_flat_books_view!("CREATE VIEW IF NOT EXISTS");
+}
+
+pub struct CreateTempFlatBooksView;
+
+/// Create the flat_books view as a temporary view of the connection, for
+/// databases opened read-only where the view cannot be stored.
+impl StaticQuery for CreateTempFlatBooksView {
+    const QUERY: &str = create_flat_books_view!("CREATE TEMP VIEW IF NOT EXISTS");
 }
@@ -2,11 +2,25 @@ use crate::queries::StaticQuery;
 
 pub struct CreateSearchIndex;
 
+/// Statement creating the search index as `$table`.
+macro_rules! create_search_index {
+    ($table:literal) => {
+        indoc::concatdoc! {"CREATE VIRTUAL TABLE IF NOT EXISTS ", $table, r#" USING fts5 (
+                "title", "sort", "authors", "series", "formats", "tags", "description", "custom"
+            );
+        "#}
+    };
+}
+
 /// Create an fts5 virtual table for search functionality.
 impl StaticQuery for CreateSearchIndex {
-    const QUERY: &str = indoc::indoc! {r#"
-        CREATE VIRTUAL TABLE IF NOT EXISTS "anserno_search_index" USING fts5 (
-            "title", "sort", "authors", "series", "formats", "description"
-        );
-    "#};
+    const QUERY: &str = create_search_index!(r#""anserno_search_index""#);
+}
+
+pub struct CreateAttachedSearchIndex;
+
+/// Create the search index in the attached `anserno` database, for libraries
+/// whose own database is opened read-only.
+impl StaticQuery for CreateAttachedSearchIndex {
+    const QUERY: &str = create_search_index!(r#""anserno"."anserno_search_index""#);
 }
@@ -9,3 +9,9 @@ pub use create_search_index::*;
 
 mod populate_search_index;
 pub use populate_search_index::*;
+
+mod search_index_state;
+pub use search_index_state::*;
+
+mod suggest_index;
+pub use suggest_index::*;
@@ -2,11 +2,11 @@ use crate::queries::StaticQuery;
 
 pub struct PopulateSearchIndex;
 
-/// Insert flat_book data into the search index.
+/// Insert flat_book data of the books not indexed yet into the search index.
 impl StaticQuery for PopulateSearchIndex {
     const QUERY: &str = indoc::indoc! {r#"
         INSERT INTO "anserno_search_index" (
-            "rowid", "title", "sort", "authors", "series", "formats", "description"
+            "rowid", "title", "sort", "authors", "series", "formats", "tags", "description"
         )
         SELECT
             "books"."id" AS "rowid",
@@ -15,6 +15,7 @@ impl StaticQuery for PopulateSearchIndex {
             RTRIM(REPLACE(GROUP_CONCAT(DISTINCT "authors"."name" || '@'), '@,', ', '), '@') AS "authors",
             RTRIM(REPLACE(GROUP_CONCAT(DISTINCT "series"."name" || '@'), '@,', ', '), '@') AS "series",
             RTRIM(REPLACE(GROUP_CONCAT(DISTINCT "data"."format" || '@'), '@,', ', '), '@') AS "formats",
+            RTRIM(REPLACE(GROUP_CONCAT(DISTINCT "tags"."name" || '@'), '@,', ', '), '@') AS "tags",
             "comments"."text" AS "description"
         FROM
             "books"
@@ -24,6 +25,10 @@ impl StaticQuery for PopulateSearchIndex {
             LEFT JOIN "authors" ON "authors"."id" = "books_authors_link"."author"
             LEFT JOIN "books_series_link" ON "books"."id" = "books_series_link"."book"
             LEFT JOIN "series" ON "series"."id" = "books_series_link"."series"
+            LEFT JOIN "books_tags_link" ON "books"."id" = "books_tags_link"."book"
+            LEFT JOIN "tags" ON "tags"."id" = "books_tags_link"."tag"
+        WHERE
+            "books"."id" NOT IN (SELECT "id" FROM "anserno_indexed_books")
         GROUP BY
             "books"."id"
     "#};
@@ -0,0 +1,86 @@
+use crate::queries::StaticQuery;
+
+/// Books whose `last_modified` differs from the one they were indexed with,
+/// or which were removed from the library.
+macro_rules! outdated_indexed_books {
+    () => {
+        r#"
+            SELECT
+                "anserno_indexed_books"."id"
+            FROM
+                "anserno_indexed_books"
+                LEFT JOIN "books" ON "books"."id" = "anserno_indexed_books"."id"
+            WHERE
+                "books"."id" IS NULL
+                OR "books"."last_modified" IS NOT "anserno_indexed_books"."last_modified"
+        "#
+    };
+}
+
+pub struct CreateAttachedSearchIndexMeta;
+
+/// Create the table of settings the attached search index was built with.
+impl StaticQuery for CreateAttachedSearchIndexMeta {
+    const QUERY: &str = indoc::indoc! {r#"
+        CREATE TABLE IF NOT EXISTS "anserno"."anserno_search_index_meta" (
+            "key" TEXT PRIMARY KEY NOT NULL,
+            "value" TEXT NOT NULL
+        );
+    "#};
+}
+
+pub struct CreateAttachedIndexedBooks;
+
+/// Create the table of books held by the attached search index, along with
+/// the `last_modified` they were indexed at.
+impl StaticQuery for CreateAttachedIndexedBooks {
+    const QUERY: &str = indoc::indoc! {r#"
+        CREATE TABLE IF NOT EXISTS "anserno"."anserno_indexed_books" (
+            "id" INTEGER PRIMARY KEY NOT NULL,
+            "last_modified" TEXT
+        );
+    "#};
+}
+
+pub struct DropAttachedSearchIndex;
+
+/// Drop the attached search index, so it is created again from scratch.
+impl StaticQuery for DropAttachedSearchIndex {
+    const QUERY: &str = r#"DROP TABLE IF EXISTS "anserno"."anserno_search_index";"#;
+}
+
+pub struct DropAttachedIndexedBooks;
+
+/// Drop the books held by the attached search index along with it.
+impl StaticQuery for DropAttachedIndexedBooks {
+    const QUERY: &str = r#"DROP TABLE IF EXISTS "anserno"."anserno_indexed_books";"#;
+}
+
+pub struct PruneSearchIndex;
+
+/// Remove outdated books from the search index.
+impl StaticQuery for PruneSearchIndex {
+    const QUERY: &str = indoc::concatdoc! {r#"
+        DELETE FROM "anserno_search_index" WHERE "rowid" IN ("#, outdated_indexed_books!(), ");"
+    };
+}
+
+pub struct PruneIndexedBooks;
+
+/// Forget outdated books, once removed from the search index.
+impl StaticQuery for PruneIndexedBooks {
+    const QUERY: &str = indoc::concatdoc! {r#"
+        DELETE FROM "anserno_indexed_books" WHERE "id" IN ("#, outdated_indexed_books!(), ");"
+    };
+}
+
+pub struct RecordIndexedBooks;
+
+/// Record the books added to the search index.
+impl StaticQuery for RecordIndexedBooks {
+    const QUERY: &str = indoc::indoc! {r#"
+        INSERT INTO "anserno_indexed_books" ("id", "last_modified")
+        SELECT "id", "last_modified" FROM "books"
+        WHERE "id" NOT IN (SELECT "id" FROM "anserno_indexed_books");
+    "#};
+}
@@ -6,9 +6,10 @@ use crate::error::{Error, Result};
 pub trait StaticQuery {
     const QUERY: &str;
 
-    fn execute(
-        conn: &sea_orm::DatabaseConnection,
-    ) -> impl ::std::future::Future<Output = Result<sea_orm::ExecResult>> {
+    fn execute<C>(conn: &C) -> impl ::std::future::Future<Output = Result<sea_orm::ExecResult>>
+    where
+        C: ConnectionTrait,
+    {
         conn.execute(Statement::from_string(
             sea_orm::DatabaseBackend::Sqlite,
             Self::QUERY,
@@ -0,0 +1,55 @@
+use crate::queries::StaticQuery;
+
+pub struct CreateAttachedSuggestIndex;
+
+/// Create the fts5 table of titles, author names and series names searched
+/// as the user types, with prefix indexes for completing partial words.
+impl StaticQuery for CreateAttachedSuggestIndex {
+    const QUERY: &str = indoc::indoc! {r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS "anserno"."anserno_suggest_index" USING fts5 (
+            "value", "kind" UNINDEXED, "id" UNINDEXED, "books" UNINDEXED,
+            prefix = '1 2 3'
+        );
+    "#};
+}
+
+pub struct DropAttachedSuggestIndex;
+
+/// Drop the attached suggest index, so it is created again from scratch.
+impl StaticQuery for DropAttachedSuggestIndex {
+    const QUERY: &str = r#"DROP TABLE IF EXISTS "anserno"."anserno_suggest_index";"#;
+}
+
+pub struct ClearSuggestIndex;
+
+/// Remove every entry of the suggest index before populating it again.
+impl StaticQuery for ClearSuggestIndex {
+    const QUERY: &str = r#"DELETE FROM "anserno_suggest_index";"#;
+}
+
+pub struct PopulateSuggestIndex;
+
+/// Insert every title, and the authors and series of at least one book,
+/// along with their number of books, into the suggest index.
+impl StaticQuery for PopulateSuggestIndex {
+    const QUERY: &str = indoc::indoc! {r#"
+        INSERT INTO "anserno_suggest_index" ("value", "kind", "id", "books")
+        SELECT "title", 'title', "id", 1 FROM "books"
+        UNION ALL
+        SELECT
+            "authors"."name", 'author', "authors"."id", COUNT(*)
+        FROM
+            "authors"
+            INNER JOIN "books_authors_link" ON "authors"."id" = "books_authors_link"."author"
+        GROUP BY
+            "authors"."id"
+        UNION ALL
+        SELECT
+            "series"."name", 'series', "series"."id", COUNT(*)
+        FROM
+            "series"
+            INNER JOIN "books_series_link" ON "series"."id" = "books_series_link"."series"
+        GROUP BY
+            "series"."id";
+    "#};
+}
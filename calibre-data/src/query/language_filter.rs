@@ -0,0 +1,127 @@
+use sea_orm::{
+    sea_query::{Expr, Query, SelectStatement},
+    ColumnTrait, EntityTrait, QueryFilter, Select,
+};
+
+use crate::entities::{
+    authors, books, books_authors_link, books_languages_link, books_publishers_link,
+    books_series_link, books_tags_link, flat_books, languages, publishers, search_index, series,
+    tags,
+};
+
+/// Sub-query selecting the ids of every book in the language `lang_code`.
+///
+/// Calibre stores languages as ISO 639-2 codes (`eng`, `deu`, `jpn`, ...),
+/// the comparison is case-insensitive.
+pub fn language_book_ids(lang_code: &str) -> SelectStatement {
+    Query::select()
+        .column((
+            books_languages_link::Entity,
+            books_languages_link::Column::Book,
+        ))
+        .from(books_languages_link::Entity)
+        .inner_join(
+            languages::Entity,
+            Expr::col((languages::Entity, languages::Column::Id)).equals((
+                books_languages_link::Entity,
+                books_languages_link::Column::LangCode,
+            )),
+        )
+        .and_where(Expr::col((languages::Entity, languages::Column::LangCode)).eq(lang_code))
+        .to_owned()
+}
+
+/// Restrict a query to records associated with books in a given language.
+pub trait LanguageFilter: EntityTrait {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self>;
+
+    /// Apply `filter_language` when a language is provided.
+    fn filter_language_opt(query: Select<Self>, lang_code: Option<&str>) -> Select<Self> {
+        match lang_code {
+            Some(lang_code) => Self::filter_language(query, lang_code),
+            None => query,
+        }
+    }
+}
+
+/// Sub-query selecting `column` from the link table `entity` for every book
+/// in the language `lang_code`.
+fn link_ids<E, C>(entity: E, column: C, book_column: C, lang_code: &str) -> SelectStatement
+where
+    E: EntityTrait<Column = C>,
+    C: ColumnTrait,
+{
+    Query::select()
+        .column(column)
+        .from(entity)
+        .and_where(book_column.in_subquery(language_book_ids(lang_code)))
+        .to_owned()
+}
+
+impl LanguageFilter for books::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(books::Column::Id.in_subquery(language_book_ids(lang_code)))
+    }
+}
+
+impl LanguageFilter for flat_books::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(flat_books::Column::Id.in_subquery(language_book_ids(lang_code)))
+    }
+}
+
+impl LanguageFilter for search_index::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(search_index::Column::BookId.in_subquery(language_book_ids(lang_code)))
+    }
+}
+
+impl LanguageFilter for languages::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(languages::Column::LangCode.eq(lang_code))
+    }
+}
+
+impl LanguageFilter for authors::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(authors::Column::Id.in_subquery(link_ids(
+            books_authors_link::Entity,
+            books_authors_link::Column::Author,
+            books_authors_link::Column::Book,
+            lang_code,
+        )))
+    }
+}
+
+impl LanguageFilter for publishers::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(publishers::Column::Id.in_subquery(link_ids(
+            books_publishers_link::Entity,
+            books_publishers_link::Column::Publisher,
+            books_publishers_link::Column::Book,
+            lang_code,
+        )))
+    }
+}
+
+impl LanguageFilter for series::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(series::Column::Id.in_subquery(link_ids(
+            books_series_link::Entity,
+            books_series_link::Column::Series,
+            books_series_link::Column::Book,
+            lang_code,
+        )))
+    }
+}
+
+impl LanguageFilter for tags::Entity {
+    fn filter_language(query: Select<Self>, lang_code: &str) -> Select<Self> {
+        query.filter(tags::Column::Id.in_subquery(link_ids(
+            books_tags_link::Entity,
+            books_tags_link::Column::Tag,
+            books_tags_link::Column::Book,
+            lang_code,
+        )))
+    }
+}
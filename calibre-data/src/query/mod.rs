@@ -1,2 +1,3 @@
 pub mod function_name;
+pub mod language_filter;
 pub mod select_alias;
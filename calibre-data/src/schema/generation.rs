@@ -0,0 +1,51 @@
+/// Oldest `user_version` whose tables anserno can read.
+pub const MIN_USER_VERSION: i32 = 20;
+
+/// Newest `user_version` known to this release.
+pub const MAX_USER_VERSION: i32 = 26;
+
+/// `user_version` at which Calibre added the viewer `annotations` table.
+pub const ANNOTATIONS_USER_VERSION: i32 = 23;
+
+/// `user_version` at which Calibre added a `link` column to the tables
+/// below, which connections to older libraries fill in with temporary views.
+pub const LINK_USER_VERSION: i32 = 25;
+
+/// Tables Calibre only added a `link` column to at `LINK_USER_VERSION`.
+pub const LINK_COLUMN_TABLES: [&str; 5] = ["languages", "publishers", "ratings", "series", "tags"];
+
+/// Calibre schema generation, derived from the database `user_version`
+/// Calibre bumps with every schema upgrade.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaGeneration {
+    /// Written by Calibre releases before 1.0.
+    Legacy,
+    /// Calibre 1.x to 4.x, without viewer annotations.
+    Classic,
+    /// Calibre 5.0 onwards, with viewer annotations.
+    Annotations,
+    /// Newer than any schema known to this release.
+    Unknown,
+}
+
+impl SchemaGeneration {
+    pub fn from_user_version(user_version: i32) -> Self {
+        match user_version {
+            ..MIN_USER_VERSION => Self::Legacy,
+            MIN_USER_VERSION..ANNOTATIONS_USER_VERSION => Self::Classic,
+            ANNOTATIONS_USER_VERSION..=MAX_USER_VERSION => Self::Annotations,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        matches!(self, Self::Classic | Self::Annotations)
+    }
+}
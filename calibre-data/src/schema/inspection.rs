@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, EntityName, EntityTrait, IdenStatic, Iterable, Statement,
+};
+
+use crate::{
+    entities::{
+        authors, books, books_authors_link, books_languages_link, books_publishers_link,
+        books_ratings_link, books_series_link, books_tags_link, comments, data, identifiers,
+        languages, preferences, publishers, ratings, series, tags,
+    },
+    error::{Error, Result},
+    schema::{SchemaError, SchemaGeneration, LINK_COLUMN_TABLES},
+};
+
+/// Structure of a Calibre metadata database, as found at connect time.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct Schema {
+    pub user_version: i32,
+    pub generation: SchemaGeneration,
+    pub tables: BTreeSet<String>,
+}
+
+impl Schema {
+    /// Read the `user_version` and table names of the database.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn inspect(conn: &DatabaseConnection) -> Result<Self> {
+        let user_version = conn
+            .query_one(Statement::from_string(
+                conn.get_database_backend(),
+                "PRAGMA user_version",
+            ))
+            .await?
+            .map(|row| row.try_get_by_index::<i32>(0))
+            .transpose()?
+            .unwrap_or_default();
+
+        let tables = conn
+            .query_all(Statement::from_string(
+                conn.get_database_backend(),
+                "SELECT name FROM sqlite_master WHERE type = 'table'",
+            ))
+            .await?
+            .iter()
+            .map(|row| row.try_get_by_index::<String>(0))
+            .collect::<::std::result::Result<BTreeSet<_>, _>>()?;
+
+        Ok(Self {
+            user_version,
+            generation: SchemaGeneration::from_user_version(user_version),
+            tables,
+        })
+    }
+
+    pub fn has_table(&self, table: &str) -> bool {
+        self.tables.contains(table)
+    }
+
+    /// Ensure the database is a Calibre library whose tables match the
+    /// entities read by anserno.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn)))]
+    pub async fn check(&self, conn: &DatabaseConnection) -> Result<()> {
+        if !self.has_table(books::Entity.table_name()) || !self.has_table(data::Entity.table_name())
+        {
+            return Err(Error::Schema(SchemaError::NotCalibre));
+        }
+
+        match self.generation {
+            SchemaGeneration::Legacy => {
+                return Err(Error::Schema(SchemaError::TooOld {
+                    user_version: self.user_version,
+                }))
+            }
+            SchemaGeneration::Unknown => {
+                return Err(Error::Schema(SchemaError::TooNew {
+                    user_version: self.user_version,
+                }))
+            }
+            SchemaGeneration::Classic | SchemaGeneration::Annotations => {}
+        }
+
+        let mut missing = Vec::new();
+
+        for (table, columns) in [
+            required_columns::<authors::Entity>(),
+            required_columns::<books::Entity>(),
+            required_columns::<books_authors_link::Entity>(),
+            required_columns::<books_languages_link::Entity>(),
+            required_columns::<books_publishers_link::Entity>(),
+            required_columns::<books_ratings_link::Entity>(),
+            required_columns::<books_series_link::Entity>(),
+            required_columns::<books_tags_link::Entity>(),
+            required_columns::<comments::Entity>(),
+            required_columns::<data::Entity>(),
+            required_columns::<identifiers::Entity>(),
+            required_columns::<languages::Entity>(),
+            required_columns::<preferences::Entity>(),
+            required_columns::<publishers::Entity>(),
+            required_columns::<ratings::Entity>(),
+            required_columns::<series::Entity>(),
+            required_columns::<tags::Entity>(),
+        ] {
+            if !self.has_table(&table) {
+                missing.push(table);
+                continue;
+            }
+
+            let found = table_columns(conn, &table).await?;
+
+            // Connections fill in the `link` columns of older libraries.
+            let optional =
+                |column: &String| column == "link" && LINK_COLUMN_TABLES.contains(&table.as_str());
+
+            missing.extend(
+                columns
+                    .into_iter()
+                    .filter(|column| !found.contains(column) && !optional(column))
+                    .map(|column| format!("{table}.{column}")),
+            );
+        }
+
+        if !missing.is_empty() {
+            return Err(Error::Schema(SchemaError::MissingColumns {
+                user_version: self.user_version,
+                missing,
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+/// Table and column names an entity selects.
+fn required_columns<E>() -> (String, Vec<String>)
+where
+    E: EntityTrait,
+{
+    (
+        E::default().table_name().to_string(),
+        E::Column::iter()
+            .map(|column| column.as_str().to_string())
+            .collect(),
+    )
+}
+
+/// Names of the columns of `table`.
+async fn table_columns(conn: &DatabaseConnection, table: &str) -> Result<BTreeSet<String>> {
+    Ok(conn
+        .query_all(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            "SELECT name FROM pragma_table_info(?)",
+            [table.into()],
+        ))
+        .await?
+        .iter()
+        .map(|row| row.try_get_by_index::<String>(0))
+        .collect::<::std::result::Result<BTreeSet<_>, _>>()?)
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::Database;
+
+    use super::*;
+    use crate::testing::METADATA_SQL;
+
+    /// In memory database built by `sql`, checked as a Calibre library.
+    async fn check(sql: &str) -> Result<Schema> {
+        let conn = Database::connect("sqlite::memory:").await.unwrap();
+        conn.execute_unprepared(sql).await.unwrap();
+
+        let schema = Schema::inspect(&conn).await?;
+        schema.check(&conn).await?;
+
+        Ok(schema)
+    }
+
+    #[tokio::test]
+    async fn supported() {
+        let schema = check(METADATA_SQL).await.unwrap();
+
+        assert_eq!(schema.user_version, 26);
+        assert_eq!(schema.generation, SchemaGeneration::Annotations);
+    }
+
+    #[tokio::test]
+    async fn supported_without_links() {
+        let sql = format!(
+            "{METADATA_SQL}\nPRAGMA user_version = 24;\n{}",
+            LINK_COLUMN_TABLES
+                .map(|table| format!("ALTER TABLE {table} DROP COLUMN link;"))
+                .join("\n")
+        );
+
+        assert!(check(&sql).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn not_calibre() {
+        let err = check("CREATE TABLE notes (id INTEGER PRIMARY KEY);").await;
+
+        assert!(matches!(err, Err(Error::Schema(SchemaError::NotCalibre))));
+    }
+
+    #[tokio::test]
+    async fn too_old() {
+        let err = check(&format!("{METADATA_SQL}\nPRAGMA user_version = 19;")).await;
+
+        assert!(matches!(
+            err,
+            Err(Error::Schema(SchemaError::TooOld { user_version: 19 }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn too_new() {
+        let err = check(&format!("{METADATA_SQL}\nPRAGMA user_version = 27;")).await;
+
+        assert!(matches!(
+            err,
+            Err(Error::Schema(SchemaError::TooNew { user_version: 27 }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn missing_columns() {
+        let err = check(&format!(
+            "{METADATA_SQL}\nALTER TABLE books DROP COLUMN uuid;\nALTER TABLE authors DROP COLUMN link;"
+        ))
+        .await;
+
+        let Err(Error::Schema(SchemaError::MissingColumns { missing, .. })) = err else {
+            panic!("expected missing columns, found: {err:?}");
+        };
+
+        assert_eq!(missing, ["authors.link", "books.uuid"]);
+    }
+}
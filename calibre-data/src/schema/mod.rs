@@ -0,0 +1,8 @@
+mod generation;
+pub use generation::*;
+
+mod schema_error;
+pub use schema_error::*;
+
+mod inspection;
+pub use inspection::*;
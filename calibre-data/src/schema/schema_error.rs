@@ -0,0 +1,44 @@
+use crate::schema::{MAX_USER_VERSION, MIN_USER_VERSION};
+
+/// Reason a database cannot be served as a Calibre library.
+#[derive(::std::clone::Clone, ::std::fmt::Debug)]
+pub enum SchemaError {
+    /// The database lacks the core Calibre tables.
+    NotCalibre,
+    /// The library predates the oldest supported schema.
+    TooOld { user_version: i32 },
+    /// The library was written by a Calibre newer than this release knows.
+    TooNew { user_version: i32 },
+    /// Tables or `table.column`s anserno reads are missing.
+    MissingColumns {
+        user_version: i32,
+        missing: Vec<String>,
+    },
+}
+
+impl ::std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotCalibre => write!(f, "not a Calibre metadata database"),
+            Self::TooOld { user_version } => write!(
+                f,
+                "library schema version {user_version} is older than the minimum supported \
+                 version {MIN_USER_VERSION}, open the library in a recent Calibre to upgrade it"
+            ),
+            Self::TooNew { user_version } => write!(
+                f,
+                "library schema version {user_version} is newer than the latest known version \
+                 {MAX_USER_VERSION}, upgrade anserno to serve it"
+            ),
+            Self::MissingColumns {
+                user_version,
+                missing,
+            } => write!(
+                f,
+                "library schema version {user_version} is missing {}, open the library in a \
+                 recent Calibre to upgrade it",
+                missing.join(", ")
+            ),
+        }
+    }
+}
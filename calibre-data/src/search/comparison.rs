@@ -0,0 +1,244 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use sea_orm::sea_query::{Expr, SimpleExpr};
+
+use crate::error::{Error, Result};
+
+/// Relational operator prefixing a numeric or date term, e.g. `rating:>=4`.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    /// Split the operator off a term value, terms without one compare for
+    /// equality.
+    pub fn split(value: &str) -> (Self, &str) {
+        [
+            (">=", Self::Ge),
+            ("<=", Self::Le),
+            ("!=", Self::Ne),
+            (">", Self::Gt),
+            ("<", Self::Lt),
+            ("=", Self::Eq),
+        ]
+        .into_iter()
+        .find_map(|(prefix, operator)| {
+            value
+                .strip_prefix(prefix)
+                .map(|value| (operator, value.trim()))
+        })
+        .unwrap_or((Self::Eq, value.trim()))
+    }
+
+    /// Boolean expression comparing `column` with `value`.
+    pub fn condition<V>(&self, column: Expr, value: V) -> SimpleExpr
+    where
+        V: Into<SimpleExpr>,
+    {
+        match self {
+            Self::Eq => column.eq(value),
+            Self::Ne => column.ne(value),
+            Self::Lt => column.lt(value),
+            Self::Le => column.lte(value),
+            Self::Gt => column.gt(value),
+            Self::Ge => column.gte(value),
+        }
+    }
+}
+
+/// Parse a number, allowing `k`, `m` and `g` (binary) multiplier suffixes
+/// when `multipliers` is set.
+pub fn parse_number(value: &str, multipliers: bool) -> Result<f64> {
+    let lowercase = value.to_lowercase();
+
+    let (number, multiplier) = match lowercase.char_indices().last() {
+        Some((index, suffix @ ('k' | 'm' | 'g'))) if multipliers => (
+            &lowercase[..index],
+            match suffix {
+                'k' => 1024.0,
+                'm' => 1024.0 * 1024.0,
+                _ => 1024.0 * 1024.0 * 1024.0,
+            },
+        ),
+        _ => (lowercase.as_str(), 1.0),
+    };
+
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .map(|number| number * multiplier)
+        .ok_or_else(|| Error::Search(format!("Expected a number, found: {value}")))
+}
+
+/// Period of days a date term refers to, from `start` up to but excluding
+/// `end`.
+#[derive(::core::marker::Copy, ::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    /// Day Calibre stores for undefined dates, 0101-01-01.
+    pub fn undefined() -> Self {
+        let start = NaiveDate::from_ymd_opt(101, 1, 1).unwrap();
+
+        Self {
+            start,
+            end: start + Days::new(1),
+        }
+    }
+
+    /// Parse a year, month or day as `YYYY`, `YYYY-MM` or `YYYY-MM-DD`, or
+    /// one of the relative `today`, `yesterday`, `thismonth` and `Ndaysago`
+    /// relative to `today`.
+    pub fn parse(value: &str, today: NaiveDate) -> Result<Self> {
+        let error = || {
+            Error::Search(format!(
+                "Expected a date as YYYY, YYYY-MM, YYYY-MM-DD, today, yesterday, thismonth or \
+                 Ndaysago, found: {value}"
+            ))
+        };
+
+        let day = |start: NaiveDate| Self {
+            start,
+            end: start + Days::new(1),
+        };
+
+        let month = |year: i32, month: u32| {
+            NaiveDate::from_ymd_opt(year, month, 1).map(|start| Self {
+                start,
+                end: start + Months::new(1),
+            })
+        };
+
+        let lowercase = value.to_lowercase();
+
+        match lowercase.as_str() {
+            "today" => return Ok(day(today)),
+            "yesterday" => return Ok(day(today - Days::new(1))),
+            "thismonth" => return month(today.year(), today.month()).ok_or_else(error),
+            _ => {}
+        }
+
+        if let Some(days) = lowercase.strip_suffix("daysago") {
+            return days
+                .parse::<u64>()
+                .ok()
+                .and_then(|days| today.checked_sub_days(Days::new(days)))
+                .map(day)
+                .ok_or_else(error);
+        }
+
+        let parts = lowercase
+            .split('-')
+            .map(|part| part.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(error)?;
+
+        match parts[..] {
+            [year] => NaiveDate::from_ymd_opt(year as i32, 1, 1).map(|start| Self {
+                start,
+                end: start + Months::new(12),
+            }),
+            [year, number] => month(year as i32, number),
+            [year, month, day_of_month] => {
+                NaiveDate::from_ymd_opt(year as i32, month, day_of_month).map(day)
+            }
+            _ => None,
+        }
+        .ok_or_else(error)
+    }
+
+    /// Boolean expression comparing the date in `column` with this period,
+    /// leaving out undefined dates unless looking for this very period.
+    ///
+    /// Calibre stores dates as text starting with `YYYY-MM-DD`, which sorts
+    /// like the dates themselves.
+    pub fn condition(&self, operator: Operator, column: Expr) -> SimpleExpr {
+        let start = self.start.format("%Y-%m-%d").to_string();
+        let end = self.end.format("%Y-%m-%d").to_string();
+        let defined = Self::undefined().end.format("%Y-%m-%d").to_string();
+
+        match operator {
+            Operator::Eq => column.clone().gte(start).and(column.lt(end)),
+            Operator::Ne => column
+                .clone()
+                .lt(start)
+                .or(column.clone().gte(end))
+                .and(column.gte(defined)),
+            Operator::Lt => column.clone().lt(start).and(column.gte(defined)),
+            Operator::Le => column.clone().lt(end).and(column.gte(defined)),
+            Operator::Gt => column.gte(end),
+            Operator::Ge => column.gte(start),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_operators() {
+        assert_eq!(Operator::split(">=4"), (Operator::Ge, "4"));
+        assert_eq!(Operator::split("<2010"), (Operator::Lt, "2010"));
+        assert_eq!(Operator::split("!=3"), (Operator::Ne, "3"));
+        assert_eq!(Operator::split("5"), (Operator::Eq, "5"));
+    }
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(parse_number("3.5", false).unwrap(), 3.5);
+        assert_eq!(parse_number("2k", true).unwrap(), 2048.0);
+        assert_eq!(parse_number("1.5M", true).unwrap(), 1.5 * 1024.0 * 1024.0);
+        assert!(parse_number("2k", false).is_err());
+        assert!(parse_number("many", true).is_err());
+    }
+
+    #[test]
+    fn parse_dates() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let today = date(2024, 3, 10);
+
+        assert_eq!(
+            DateRange::parse("2010", today).unwrap(),
+            DateRange {
+                start: date(2010, 1, 1),
+                end: date(2011, 1, 1),
+            }
+        );
+        assert_eq!(
+            DateRange::parse("2010-12", today).unwrap(),
+            DateRange {
+                start: date(2010, 12, 1),
+                end: date(2011, 1, 1),
+            }
+        );
+        assert_eq!(
+            DateRange::parse("3daysago", today).unwrap(),
+            DateRange {
+                start: date(2024, 3, 7),
+                end: date(2024, 3, 8),
+            }
+        );
+        assert_eq!(
+            DateRange::parse("thismonth", today).unwrap().start,
+            date(2024, 3, 1)
+        );
+        assert!(DateRange::parse("2010-13", today).is_err());
+        assert!(DateRange::parse("last week", today).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+
+use sea_orm::{sea_query::SimpleExpr, ColumnTrait};
+
+use crate::{
+    custom_columns::CustomColumns,
+    error::{Error, Result},
+    search::{tokenize, Field, Token},
+};
+
+/// Saved searches may reference each other, bound the expansion depth.
+const MAX_SEARCH_DEPTH: usize = 16;
+
+/// Parsed Calibre search expression.
+///
+/// Terms are combined with `and`, `or` and `not`, adjacent terms are joined
+/// with an implicit `and`, and parentheses group sub-expressions. Terms
+/// follow Calibre's syntax, see [`Field::matcher`].
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Term { field: Field, value: String },
+}
+
+impl ::std::str::FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+
+        let expression = parser.or_expression()?;
+
+        match parser.peek() {
+            None => Ok(expression),
+            Some(Token::RightParen) => Err(Error::Search("Unbalanced ')'".to_string())),
+            Some(token) => Err(Error::Search(format!("Unexpected token: {token}"))),
+        }
+    }
+}
+
+impl Expression {
+    /// Compile the expression into a condition on a book id `column`.
+    ///
+    /// `saved_searches` resolves `search:name` references, and
+    /// `custom_columns` the `#label` fields.
+    pub fn condition<C>(
+        &self,
+        column: C,
+        saved_searches: &BTreeMap<String, String>,
+        custom_columns: &CustomColumns,
+    ) -> Result<SimpleExpr>
+    where
+        C: ColumnTrait,
+    {
+        self.condition_with_depth(column, saved_searches, custom_columns, 0)
+    }
+
+    /// Whether any term references a saved search, `search:name`.
+    pub fn references_saved_searches(&self) -> bool {
+        match self {
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.references_saved_searches() || right.references_saved_searches()
+            }
+            Self::Not(expression) => expression.references_saved_searches(),
+            Self::Term { field, .. } => *field == Field::Search,
+        }
+    }
+
+    fn condition_with_depth<C>(
+        &self,
+        column: C,
+        saved_searches: &BTreeMap<String, String>,
+        custom_columns: &CustomColumns,
+        depth: usize,
+    ) -> Result<SimpleExpr>
+    where
+        C: ColumnTrait,
+    {
+        match self {
+            Self::And(left, right) => Ok(left
+                .condition_with_depth(column, saved_searches, custom_columns, depth)?
+                .and(right.condition_with_depth(column, saved_searches, custom_columns, depth)?)),
+
+            Self::Or(left, right) => Ok(left
+                .condition_with_depth(column, saved_searches, custom_columns, depth)?
+                .or(right.condition_with_depth(column, saved_searches, custom_columns, depth)?)),
+
+            Self::Not(expression) => Ok(expression
+                .condition_with_depth(column, saved_searches, custom_columns, depth)?
+                .not()),
+
+            Self::Term {
+                field: Field::Search,
+                value,
+            } => {
+                if depth >= MAX_SEARCH_DEPTH {
+                    return Err(Error::Search(format!(
+                        "Saved search nesting too deep at: {value}"
+                    )));
+                }
+
+                let name = value.strip_prefix('=').unwrap_or(value);
+
+                let search = saved_searches
+                    .get(name)
+                    .or_else(|| {
+                        saved_searches
+                            .iter()
+                            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                            .map(|(_, search)| search)
+                    })
+                    .ok_or_else(|| Error::Search(format!("Unknown saved search: {name}")))?;
+
+                search.parse::<Self>()?.condition_with_depth(
+                    column,
+                    saved_searches,
+                    custom_columns,
+                    depth + 1,
+                )
+            }
+
+            Self::Term {
+                field: Field::Any,
+                value,
+            } => {
+                let matcher = Field::Any.matcher(value)?;
+
+                Ok(Field::ANY
+                    .iter()
+                    .filter_map(|field| field.book_ids(&matcher))
+                    .map(|book_ids| column.in_subquery(book_ids))
+                    .reduce(SimpleExpr::or)
+                    .unwrap())
+            }
+
+            // `field:false` selects the books `field:true` does not.
+            Self::Term { field, value } if value.eq_ignore_ascii_case("false") => {
+                Ok(column.not_in_subquery(field.term_book_ids("true", custom_columns)?))
+            }
+
+            Self::Term { field, value } => {
+                Ok(column.in_subquery(field.term_book_ids(value, custom_columns)?))
+            }
+        }
+    }
+}
+
+/// Recursive descent parser over expression tokens, `or` binds loosest and
+/// `not` tightest.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or_expression(&mut self) -> Result<Expression> {
+        let mut expression = self.and_expression()?;
+
+        while let Some(Token::Or) = self.peek() {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.and_expression()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn and_expression(&mut self) -> Result<Expression> {
+        let mut expression = self.not_expression()?;
+
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Not | Token::LeftParen | Token::Term { .. }) => {}
+                _ => break,
+            }
+
+            expression = Expression::And(Box::new(expression), Box::new(self.not_expression()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn not_expression(&mut self) -> Result<Expression> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.not_expression()?))),
+
+            Some(Token::LeftParen) => {
+                let expression = self.or_expression()?;
+
+                match self.next() {
+                    Some(Token::RightParen) => Ok(expression),
+                    _ => Err(Error::Search("Missing ')'".to_string())),
+                }
+            }
+
+            Some(Token::Term { field, value, .. }) => Ok(Expression::Term {
+                field: match field {
+                    Some(field) => field.parse()?,
+                    None => Field::Any,
+                },
+                value,
+            }),
+
+            Some(token) => Err(Error::Search(format!("Unexpected token: {token}"))),
+
+            None => Err(Error::Search("Unexpected end of expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sea_orm::{EntityTrait, QueryFilter, QueryOrder};
+
+    use super::*;
+    use crate::{entities::books, testing};
+
+    fn term(field: Field, value: &str) -> Expression {
+        Expression::Term {
+            field,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let expression: Expression = "tags:fantasy or not tags:read and title:earthsea"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            expression,
+            Expression::Or(
+                Box::new(term(Field::Tags, "fantasy")),
+                Box::new(Expression::And(
+                    Box::new(Expression::Not(Box::new(term(Field::Tags, "read")))),
+                    Box::new(term(Field::Title, "earthsea")),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn parse_quoted_and_grouped() {
+        let expression: Expression =
+            r#"authors:"=Ursula K. Le Guin" (series:earthsea OR "wizard")"#
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            expression,
+            Expression::And(
+                Box::new(term(Field::Authors, "=Ursula K. Le Guin")),
+                Box::new(Expression::Or(
+                    Box::new(term(Field::Series, "earthsea")),
+                    Box::new(term(Field::Any, "wizard")),
+                )),
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn conditions_on_custom_columns_and_identifiers() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database = testing::create_library(directory.path()).await.unwrap();
+        let conn = sea_orm::Database::connect(format!("sqlite://{}", database.display()))
+            .await
+            .unwrap();
+        let custom_columns = CustomColumns::load(&conn).await.unwrap();
+
+        let condition = |search: &str| {
+            search.parse::<Expression>()?.condition(
+                books::Column::Id,
+                &BTreeMap::new(),
+                &custom_columns,
+            )
+        };
+
+        for (search, expected) in [
+            ("#shelf:attic", vec![1]),
+            (r#"#shelf:"=Living room""#, vec![1, 2]),
+            ("#shelf:false", vec![3]),
+            ("#pages:>=200", vec![1]),
+            ("#read:yes", vec![1]),
+            ("#read:no", vec![3]),
+            ("#read:false", vec![2]),
+            ("#arc:early", vec![1]),
+            ("#finished:2023", vec![1]),
+            ("identifiers:isbn:978054", vec![1]),
+            ("identifiers:=isbn:", vec![1, 3]),
+            ("identifiers:goodreads", vec![1]),
+            ("identifiers::=9783518380277", vec![3]),
+            ("identifiers:false", vec![2]),
+            ("not #shelf:attic and identifiers:isbn", vec![3]),
+        ] {
+            let ids = books::Entity::find()
+                .filter(condition(search).unwrap())
+                .order_by_asc(books::Column::Id)
+                .all(&conn)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|book| book.id)
+                .collect::<Vec<_>>();
+
+            assert_eq!(ids, expected, "{search}");
+        }
+
+        assert!(condition("#nosuchcolumn:x").is_err());
+        assert!(condition("#read:maybe").is_err());
+        assert!(condition("#pages:>many").is_err());
+    }
+
+    #[test]
+    fn references_saved_searches() {
+        let references = |search: &str| {
+            search
+                .parse::<Expression>()
+                .unwrap()
+                .references_saved_searches()
+        };
+
+        assert!(references("tags:fantasy and not search:Unread"));
+        assert!(references("(title:earthsea or search:=\"Le Guin\")"));
+        assert!(!references("tags:fantasy or searching"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("".parse::<Expression>().is_err());
+        assert!("(tags:read".parse::<Expression>().is_err());
+        assert!("tags:read)".parse::<Expression>().is_err());
+        assert!("title:\"unterminated".parse::<Expression>().is_err());
+        assert!("nonsense:value".parse::<Expression>().is_err());
+        assert!("tags:read and".parse::<Expression>().is_err());
+    }
+}
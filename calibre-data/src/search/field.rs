@@ -0,0 +1,453 @@
+use sea_orm::{
+    sea_query::{Alias, Expr, Func, Query, SelectStatement},
+    EntityTrait,
+};
+
+use crate::{
+    custom_columns::{CustomColumn, CustomColumns, Datatype},
+    entities::{
+        authors, books, books_authors_link, books_languages_link, books_publishers_link,
+        books_ratings_link, books_series_link, books_tags_link, comments, data, identifiers,
+        languages, publishers, ratings, series, tags,
+    },
+    error::{Error, Result},
+    search::{parse_number, DateRange, Matcher, Operator},
+};
+
+/// Book metadata field addressable from a Calibre search expression.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub enum Field {
+    /// Bare terms match against any of the main text fields.
+    Any,
+    Authors,
+    Comments,
+    /// Whether the book has a cover, `cover:true`.
+    Cover,
+    /// Custom column by its label, `#label:value`.
+    Custom(String),
+    Formats,
+    /// Identifier by type and value, `identifiers:type:value`.
+    Identifiers,
+    Languages,
+    LastModified,
+    Pubdate,
+    Publishers,
+    /// Rating in stars, stored as half stars.
+    Rating,
+    /// Reference to a saved search, `search:"name"`.
+    Search,
+    Series,
+    SeriesIndex,
+    /// Size in bytes of the largest format.
+    Size,
+    Tags,
+    /// Date the book was added to the library.
+    Timestamp,
+    Title,
+}
+
+impl ::std::str::FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "author" | "authors" => Ok(Self::Authors),
+            "comment" | "comments" => Ok(Self::Comments),
+            "cover" => Ok(Self::Cover),
+            "date" | "timestamp" => Ok(Self::Timestamp),
+            "format" | "formats" => Ok(Self::Formats),
+            "identifier" | "identifiers" => Ok(Self::Identifiers),
+            "language" | "languages" => Ok(Self::Languages),
+            "last_modified" => Ok(Self::LastModified),
+            "pubdate" => Ok(Self::Pubdate),
+            "publisher" | "publishers" => Ok(Self::Publishers),
+            "rating" => Ok(Self::Rating),
+            "search" => Ok(Self::Search),
+            "series" => Ok(Self::Series),
+            "series_index" => Ok(Self::SeriesIndex),
+            "size" => Ok(Self::Size),
+            "tag" | "tags" => Ok(Self::Tags),
+            "title" => Ok(Self::Title),
+            _ => match s.strip_prefix('#').filter(|label| !label.is_empty()) {
+                Some(label) => Ok(Self::Custom(label.to_string())),
+                None => Err(Error::Search(format!("Unknown search field: {s}"))),
+            },
+        }
+    }
+}
+
+impl Field {
+    /// Fields searched by a bare term.
+    pub const ANY: [Self; 5] = [
+        Self::Title,
+        Self::Authors,
+        Self::Series,
+        Self::Tags,
+        Self::Publishers,
+    ];
+
+    /// Matcher for a term `value` of this field.
+    ///
+    /// Text fields match substrings, or exactly and by regular expression
+    /// with `=` and `~` prefixes. Numeric and date fields compare with an
+    /// optional relational operator, dates against the year, month or day
+    /// given. `true` matches books with any value for the field.
+    pub fn matcher(&self, value: &str) -> Result<Matcher> {
+        let present = *self != Self::Any && value.eq_ignore_ascii_case("true");
+        let (operator, operand) = Operator::split(value);
+
+        // Name the field in value errors.
+        let field_error = |err| match err {
+            Error::Search(msg) => Error::Search(format!("{self:?}: {msg}")),
+            err => err,
+        };
+
+        let number = |multipliers| parse_number(operand, multipliers).map_err(field_error);
+
+        let date =
+            || DateRange::parse(operand, chrono::Utc::now().date_naive()).map_err(field_error);
+
+        match self {
+            Self::Cover | Self::Rating | Self::SeriesIndex | Self::Size if present => {
+                Ok(Matcher::Number(Operator::Gt, 0.0))
+            }
+            Self::LastModified | Self::Pubdate | Self::Timestamp if present => {
+                Ok(Matcher::Date(Operator::Gt, DateRange::undefined()))
+            }
+            _ if present => Ok(Matcher::Present),
+
+            Self::Cover => Err(Error::Search(format!(
+                "Cover: Expected true or false, found: {value}"
+            ))),
+            Self::Rating => Ok(Matcher::Number(operator, number(false)? * 2.0)),
+            Self::SeriesIndex => Ok(Matcher::Number(operator, number(false)?)),
+            Self::Size => Ok(Matcher::Number(operator, number(true)?)),
+            Self::LastModified | Self::Pubdate | Self::Timestamp => {
+                Ok(Matcher::Date(operator, date()?))
+            }
+
+            _ => Matcher::parse(value),
+        }
+    }
+
+    /// Sub-query selecting the ids of books whose field satisfies the term
+    /// `value`, resolving custom columns in `custom_columns`.
+    ///
+    /// Fails for the pseudo-fields `Any` and `Search`, which are expanded by
+    /// the expression compiler.
+    pub fn term_book_ids(
+        &self,
+        value: &str,
+        custom_columns: &CustomColumns,
+    ) -> Result<SelectStatement> {
+        match self {
+            Self::Custom(label) => {
+                let column = custom_columns
+                    .get(label)
+                    .ok_or_else(|| Error::Search(format!("Unknown custom column: #{label}")))?;
+
+                Ok(custom_book_ids(column, &custom_matcher(column, value)?))
+            }
+
+            Self::Identifiers => identifier_book_ids(value),
+
+            field => field
+                .book_ids(&field.matcher(value)?)
+                .ok_or_else(|| Error::Search(format!("Unsupported search field: {field:?}"))),
+        }
+    }
+
+    /// Sub-query selecting the ids of books whose field satisfies `matcher`.
+    ///
+    /// Returns `None` for the pseudo-fields `Any` and `Search`, which are
+    /// expanded by the expression compiler, and for custom columns and
+    /// identifiers, whose terms are only matched by [`Field::term_book_ids`].
+    pub fn book_ids(&self, matcher: &Matcher) -> Option<SelectStatement> {
+        match self {
+            Self::Any | Self::Custom(_) | Self::Identifiers | Self::Search => None,
+
+            Self::Cover => Some(books_book_ids(books::Column::HasCover, matcher)),
+            Self::LastModified => Some(books_book_ids(books::Column::LastModified, matcher)),
+            Self::Pubdate => Some(books_book_ids(books::Column::Pubdate, matcher)),
+            Self::SeriesIndex => Some(books_book_ids(books::Column::SeriesIndex, matcher)),
+            Self::Timestamp => Some(books_book_ids(books::Column::Timestamp, matcher)),
+
+            Self::Rating => Some(linked_book_ids(
+                (books_ratings_link::Entity, books_ratings_link::Column::Book),
+                (
+                    books_ratings_link::Entity,
+                    books_ratings_link::Column::Rating,
+                ),
+                (ratings::Entity, ratings::Column::Id),
+                (ratings::Entity, ratings::Column::Rating),
+                matcher,
+            )),
+
+            Self::Size => Some(
+                Query::select()
+                    .column((data::Entity, data::Column::Book))
+                    .from(data::Entity)
+                    .group_by_col((data::Entity, data::Column::Book))
+                    .and_having(matcher.condition(Expr::expr(Func::max(Expr::col((
+                        data::Entity,
+                        data::Column::UncompressedSize,
+                    ))))))
+                    .to_owned(),
+            ),
+
+            Self::Title => Some(books_book_ids(books::Column::Title, matcher)),
+
+            Self::Comments => Some(
+                Query::select()
+                    .column((comments::Entity, comments::Column::Book))
+                    .from(comments::Entity)
+                    .and_where(
+                        matcher.condition(Expr::col((comments::Entity, comments::Column::Text))),
+                    )
+                    .to_owned(),
+            ),
+
+            Self::Formats => Some(
+                Query::select()
+                    .column((data::Entity, data::Column::Book))
+                    .from(data::Entity)
+                    .and_where(matcher.condition(Expr::col((data::Entity, data::Column::Format))))
+                    .to_owned(),
+            ),
+
+            Self::Authors => Some(linked_book_ids(
+                (books_authors_link::Entity, books_authors_link::Column::Book),
+                (
+                    books_authors_link::Entity,
+                    books_authors_link::Column::Author,
+                ),
+                (authors::Entity, authors::Column::Id),
+                (authors::Entity, authors::Column::Name),
+                matcher,
+            )),
+
+            Self::Languages => Some(linked_book_ids(
+                (
+                    books_languages_link::Entity,
+                    books_languages_link::Column::Book,
+                ),
+                (
+                    books_languages_link::Entity,
+                    books_languages_link::Column::LangCode,
+                ),
+                (languages::Entity, languages::Column::Id),
+                (languages::Entity, languages::Column::LangCode),
+                matcher,
+            )),
+
+            Self::Publishers => Some(linked_book_ids(
+                (
+                    books_publishers_link::Entity,
+                    books_publishers_link::Column::Book,
+                ),
+                (
+                    books_publishers_link::Entity,
+                    books_publishers_link::Column::Publisher,
+                ),
+                (publishers::Entity, publishers::Column::Id),
+                (publishers::Entity, publishers::Column::Name),
+                matcher,
+            )),
+
+            Self::Series => Some(linked_book_ids(
+                (books_series_link::Entity, books_series_link::Column::Book),
+                (books_series_link::Entity, books_series_link::Column::Series),
+                (series::Entity, series::Column::Id),
+                (series::Entity, series::Column::Name),
+                matcher,
+            )),
+
+            Self::Tags => Some(linked_book_ids(
+                (books_tags_link::Entity, books_tags_link::Column::Book),
+                (books_tags_link::Entity, books_tags_link::Column::Tag),
+                (tags::Entity, tags::Column::Id),
+                (tags::Entity, tags::Column::Name),
+                matcher,
+            )),
+        }
+    }
+}
+
+/// Sub-query selecting books whose own `column` satisfies `matcher`.
+fn books_book_ids(column: books::Column, matcher: &Matcher) -> SelectStatement {
+    Query::select()
+        .column((books::Entity, books::Column::Id))
+        .from(books::Entity)
+        .and_where(matcher.condition(Expr::col((books::Entity, column))))
+        .to_owned()
+}
+
+/// Matcher for a term `value` of the custom `column`, interpreted as for
+/// the built-in field of the same datatype. Yes/no columns also match `yes`
+/// and `no`.
+fn custom_matcher(column: &CustomColumn, value: &str) -> Result<Matcher> {
+    if value.eq_ignore_ascii_case("true") {
+        return Ok(Matcher::Present);
+    }
+
+    let (operator, operand) = Operator::split(value);
+
+    // Name the column in value errors.
+    let column_error = |err| match err {
+        Error::Search(msg) => Error::Search(format!("#{}: {msg}", column.label)),
+        err => err,
+    };
+
+    let number = |multipliers| parse_number(operand, multipliers).map_err(column_error);
+
+    match column.datatype {
+        Datatype::Bool => match operand.to_lowercase().as_str() {
+            "yes" | "checked" => Ok(Matcher::Number(Operator::Eq, 1.0)),
+            "no" | "unchecked" => Ok(Matcher::Number(Operator::Eq, 0.0)),
+            _ => Err(Error::Search(format!(
+                "#{}: Expected true, false, yes or no, found: {value}",
+                column.label
+            ))),
+        },
+        Datatype::Float | Datatype::Int => Ok(Matcher::Number(operator, number(false)?)),
+        Datatype::Rating => Ok(Matcher::Number(operator, number(false)? * 2.0)),
+        Datatype::Datetime => Ok(Matcher::Date(
+            operator,
+            DateRange::parse(operand, chrono::Utc::now().date_naive()).map_err(column_error)?,
+        )),
+        Datatype::Composite => Err(Error::Search(format!(
+            "#{}: Composite columns can not be searched",
+            column.label
+        ))),
+        Datatype::Comments | Datatype::Enumeration | Datatype::Series | Datatype::Text => {
+            Matcher::parse(value)
+        }
+    }
+}
+
+/// Sub-query selecting books whose value of the custom `column` satisfies
+/// `matcher`, through its link table for normalized columns.
+fn custom_book_ids(column: &CustomColumn, matcher: &Matcher) -> SelectStatement {
+    let table = Alias::new(column.table_name());
+    let book_table = Alias::new(column.book_table_name());
+
+    let mut query = Query::select();
+
+    query
+        .column((book_table.clone(), Alias::new("book")))
+        .from(book_table.clone())
+        .and_where(matcher.condition(Expr::col((table.clone(), Alias::new("value")))));
+
+    if column.normalized {
+        query.inner_join(
+            table.clone(),
+            Expr::col((table, Alias::new("id"))).equals((book_table, Alias::new("value"))),
+        );
+    }
+
+    query
+}
+
+/// Sub-query selecting books with an identifier matching `value`, written
+/// as `type:value` where either part may be left empty to match any, or as
+/// a type alone. `true` matches books with any identifier.
+fn identifier_book_ids(value: &str) -> Result<SelectStatement> {
+    let mut query = Query::select();
+
+    query
+        .column((identifiers::Entity, identifiers::Column::Book))
+        .from(identifiers::Entity);
+
+    if value.eq_ignore_ascii_case("true") {
+        return Ok(query);
+    }
+
+    let (kind, value) = value.split_once(':').unwrap_or((value, ""));
+
+    for (column, term) in [
+        (identifiers::Column::Kind, kind),
+        (identifiers::Column::Val, value),
+    ] {
+        if !term.is_empty() {
+            query.and_where(
+                Matcher::parse(term)?.condition(Expr::col((identifiers::Entity, column))),
+            );
+        }
+    }
+
+    Ok(query)
+}
+
+/// Sub-query selecting books through a link table, matching `value` of the
+/// linked entity.
+fn linked_book_ids<L, E>(
+    book: (L, L::Column),
+    foreign_key: (L, L::Column),
+    id: (E, E::Column),
+    value: (E, E::Column),
+    matcher: &Matcher,
+) -> SelectStatement
+where
+    L: EntityTrait,
+    E: EntityTrait,
+{
+    Query::select()
+        .column(book)
+        .from(book.0)
+        .inner_join(id.0, Expr::col(id).equals(foreign_key))
+        .and_where(matcher.condition(Expr::col(value)))
+        .to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matchers() {
+        assert_eq!(
+            Field::Rating.matcher(">=4").unwrap(),
+            Matcher::Number(Operator::Ge, 8.0)
+        );
+        assert_eq!(
+            Field::Size.matcher("<1k").unwrap(),
+            Matcher::Number(Operator::Lt, 1024.0)
+        );
+        assert_eq!(
+            Field::Tags.matcher("~^sci").unwrap(),
+            Matcher::Regex("^sci".to_string())
+        );
+        assert_eq!(
+            Field::Authors.matcher("=Tolkien").unwrap(),
+            Matcher::Exact("Tolkien".to_string())
+        );
+        assert_eq!(Field::Tags.matcher("TRUE").unwrap(), Matcher::Present);
+        assert_eq!(
+            Field::Pubdate.matcher("true").unwrap(),
+            Matcher::Date(Operator::Gt, DateRange::undefined())
+        );
+        assert_eq!(
+            Field::Any.matcher("true").unwrap(),
+            Matcher::Contains("true".to_string())
+        );
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!("tags".parse::<Field>().unwrap(), Field::Tags);
+        assert_eq!(
+            "#shelf".parse::<Field>().unwrap(),
+            Field::Custom("shelf".to_string())
+        );
+        assert_eq!("identifiers".parse::<Field>().unwrap(), Field::Identifiers);
+        assert!("#".parse::<Field>().is_err());
+    }
+
+    #[test]
+    fn matcher_errors() {
+        assert!(Field::Rating.matcher(">=many").is_err());
+        assert!(Field::Pubdate.matcher("<someday").is_err());
+        assert!(Field::Cover.matcher("yes").is_err());
+        assert!(Field::Title.matcher("~(unclosed").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    error::{Error, Result},
+    search::{Expression, Field},
+};
+
+/// Search query compiled into an fts5 `MATCH` expression over the search
+/// index.
+///
+/// Queries share the syntax of Calibre search expressions: `author:`,
+/// `title:`, `series:`, `format:`, `tag:` and `comments:` prefixes scope a
+/// term to one column of the index, `"quoted phrases"` match consecutive
+/// words, a trailing `*` matches word prefixes, and terms combine with
+/// `AND`, `OR`, `NOT` and parentheses. Every term is emitted as an fts5
+/// string, so no input reaches the index as fts5 syntax.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq, ::std::cmp::Eq)]
+pub struct FullTextQuery(String);
+
+impl ::std::str::FromStr for FullTextQuery {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        compile(&s.parse()?).map(Self)
+    }
+}
+
+impl ::std::fmt::Display for FullTextQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FullTextQuery {
+    /// The fts5 expression, to be bound as the right hand side of `MATCH`.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Search index column of a field, `None` for bare terms matching every
+/// column.
+fn column(field: &Field) -> Result<Option<&'static str>> {
+    match field {
+        Field::Any => Ok(None),
+        Field::Authors => Ok(Some("authors")),
+        Field::Comments => Ok(Some("description")),
+        Field::Formats => Ok(Some("formats")),
+        Field::Series => Ok(Some("series")),
+        Field::Tags => Ok(Some("tags")),
+        Field::Title => Ok(Some("title")),
+        Field::Cover
+        | Field::Custom(_)
+        | Field::Identifiers
+        | Field::Languages
+        | Field::LastModified
+        | Field::Pubdate
+        | Field::Publishers
+        | Field::Rating
+        | Field::Search
+        | Field::SeriesIndex
+        | Field::Size
+        | Field::Timestamp => Err(Error::Search(format!(
+            "{field:?} can not be used in a full text search, use one of author, title, series, \
+             format, tag or comments"
+        ))),
+    }
+}
+
+fn compile(expression: &Expression) -> Result<String> {
+    match expression {
+        Expression::Term { field, value } => {
+            let (value, prefix) = match value.strip_suffix('*') {
+                Some(value) => (value, " *"),
+                None => (value.as_str(), ""),
+            };
+
+            if value.trim().is_empty() {
+                return Err(Error::Search(match column(field)? {
+                    Some(column) => format!("Missing search term after {column}:"),
+                    None => "Empty search term".to_string(),
+                }));
+            }
+
+            let phrase = format!("\"{}\"{prefix}", value.replace('"', "\"\""));
+
+            Ok(match column(field)? {
+                Some(column) => format!("{{{column}}} : {phrase}"),
+                None => phrase,
+            })
+        }
+
+        Expression::Or(left, right) => {
+            if matches!(**left, Expression::Not(_)) || matches!(**right, Expression::Not(_)) {
+                return Err(Error::Search(
+                    "NOT can not be an alternative of OR, group it with the terms it excludes \
+                     from, e.g. (wizard NOT tag:read) OR dragon"
+                        .to_string(),
+                ));
+            }
+
+            Ok(format!("({}) OR ({})", compile(left)?, compile(right)?))
+        }
+
+        Expression::And(..) => {
+            let mut included = Vec::new();
+            let mut excluded = Vec::new();
+
+            for conjunct in conjuncts_of(expression) {
+                match conjunct {
+                    Expression::Not(expression) => excluded.push(compile(expression)?),
+                    expression => included.push(compile(expression)?),
+                }
+            }
+
+            if included.is_empty() {
+                return Err(not_alone());
+            }
+
+            let included = format!("({})", included.join(") AND ("));
+
+            // NOT binds tighter than AND and OR in fts5.
+            Ok(match excluded.is_empty() {
+                true => included,
+                false => format!("({included}) NOT (({}))", excluded.join(") OR (")),
+            })
+        }
+
+        Expression::Not(_) => Err(not_alone()),
+    }
+}
+
+/// Operands of a chain of `AND`s, which fts5 needs to gather so every `NOT`
+/// excludes from the terms it is combined with.
+fn conjuncts_of(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::And(left, right) => {
+            let mut conjuncts = conjuncts_of(left);
+            conjuncts.extend(conjuncts_of(right));
+            conjuncts
+        }
+        expression => vec![expression],
+    }
+}
+
+fn not_alone() -> Error {
+    Error::Search(
+        "NOT needs terms to exclude from, e.g. wizard NOT tag:read, as the search index can not \
+         list every book lacking a term"
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compiled(query: &str) -> String {
+        query.parse::<FullTextQuery>().unwrap().to_string()
+    }
+
+    #[test]
+    fn compile_column_filters() {
+        assert_eq!(
+            compiled(r#"author:"Le Guin" series:earthsea"#),
+            r#"({authors} : "Le Guin") AND ({series} : "earthsea")"#
+        );
+        assert_eq!(
+            compiled("tag:fantasy OR (format:epub wiz*)"),
+            r#"({tags} : "fantasy") OR (({formats} : "epub") AND ("wiz" *))"#
+        );
+    }
+
+    #[test]
+    fn compile_escapes_terms() {
+        assert_eq!(compiled(r#"title:"a \"b\"""#), r#"{title} : "a ""b""""#);
+        assert_eq!(compiled("c++ NEAR"), r#"("c++") AND ("NEAR")"#);
+    }
+
+    #[test]
+    fn compile_not() {
+        assert_eq!(
+            compiled("NOT tag:read wizard NOT format:pdf"),
+            r#"(("wizard")) NOT (({tags} : "read") OR ({formats} : "pdf"))"#
+        );
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert!("NOT tag:read".parse::<FullTextQuery>().is_err());
+        assert!("wizard OR NOT tag:read".parse::<FullTextQuery>().is_err());
+        assert!("publisher:tor".parse::<FullTextQuery>().is_err());
+        assert!("title:".parse::<FullTextQuery>().is_err());
+        assert!("(title:earthsea".parse::<FullTextQuery>().is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use sea_orm::{ConnectionTrait, Statement, Value};
+
+use crate::{error::Result, search::FullTextQuery};
+
+/// Markers `highlight()` and `snippet()` wrap matched text in, control
+/// characters which do not occur in book metadata.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// Words around the match `snippet()` extracts.
+const SNIPPET_TOKENS: u32 = 24;
+
+/// Piece of highlighted text, `matched` by the search or not.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq, serde::Serialize)]
+pub struct Fragment {
+    pub text: String,
+    pub matched: bool,
+}
+
+/// Why a book matched a full text search: its title with the matches
+/// highlighted, and the passage of its metadata matching best.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq, serde::Serialize)]
+pub struct SearchHighlight {
+    pub title: Vec<Fragment>,
+    /// Best matching passage, empty when it is the title itself or holds no
+    /// match.
+    pub snippet: Vec<Fragment>,
+}
+
+impl SearchHighlight {
+    /// Highlights of `book_ids` matching `query`, by book id.
+    ///
+    /// Only meant for a page of hits, as the query is run again for them.
+    pub async fn find<C>(
+        conn: &C,
+        query: &FullTextQuery,
+        book_ids: &[i32],
+    ) -> Result<HashMap<i32, Self>>
+    where
+        C: ConnectionTrait,
+    {
+        if book_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let sql = format!(
+            indoc::indoc! {r#"
+                SELECT
+                    "rowid",
+                    highlight("anserno_search_index", 0, ?, ?),
+                    snippet("anserno_search_index", -1, ?, ?, '…', {})
+                FROM
+                    "anserno_search_index"
+                WHERE
+                    "anserno_search_index" MATCH ?
+                    AND "rowid" IN ({})
+            "#},
+            SNIPPET_TOKENS,
+            vec!["?"; book_ids.len()].join(", ")
+        );
+
+        let markers = [MATCH_START, MATCH_END, MATCH_START, MATCH_END]
+            .into_iter()
+            .map(|marker| Value::from(marker.to_string()));
+
+        let values = markers
+            .chain([Value::from(query.as_str())])
+            .chain(book_ids.iter().map(|&book_id| Value::from(book_id)))
+            .collect::<Vec<_>>();
+
+        conn.query_all(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            sql,
+            values,
+        ))
+        .await?
+        .into_iter()
+        .map(|row| {
+            let book_id = row.try_get_by_index::<i32>(0)?;
+            let title = row
+                .try_get_by_index::<Option<String>>(1)?
+                .unwrap_or_default();
+            let snippet = row
+                .try_get_by_index::<Option<String>>(2)?
+                .unwrap_or_default();
+
+            let title = fragments(&title);
+            let mut snippet = fragments(&snippet);
+
+            if !snippet.iter().any(|fragment| fragment.matched)
+                || plain_text(&snippet) == plain_text(&title)
+            {
+                snippet.clear();
+            }
+
+            Ok((book_id, Self { title, snippet }))
+        })
+        .collect()
+    }
+}
+
+/// Split marked text into fragments.
+fn fragments(text: &str) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    let mut matched = false;
+    let mut current = String::new();
+
+    for c in text.chars() {
+        match c {
+            MATCH_START | MATCH_END => {
+                if !current.is_empty() {
+                    fragments.push(Fragment {
+                        text: ::std::mem::take(&mut current),
+                        matched,
+                    });
+                }
+                matched = c == MATCH_START;
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        fragments.push(Fragment {
+            text: current,
+            matched,
+        });
+    }
+
+    fragments
+}
+
+fn plain_text(fragments: &[Fragment]) -> String {
+    fragments
+        .iter()
+        .map(|fragment| fragment.text.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        custom_columns::CustomColumns,
+        library::{connect_with_sidecar, DatabaseAccess, SearchIndexUpdate},
+        testing,
+    };
+
+    #[test]
+    fn split_fragments() {
+        let fragment = |text: &str, matched| Fragment {
+            text: text.to_string(),
+            matched,
+        };
+
+        assert_eq!(
+            fragments("A \u{2}Wizard\u{3} of \u{2}Earthsea\u{3}"),
+            vec![
+                fragment("A ", false),
+                fragment("Wizard", true),
+                fragment(" of ", false),
+                fragment("Earthsea", true),
+            ]
+        );
+        assert_eq!(fragments("Plain"), vec![fragment("Plain", false)]);
+    }
+
+    #[tokio::test]
+    async fn snippets_hold_no_markup() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let database = testing::create_library(directory.path()).await.unwrap();
+        testing::execute_sql(
+            &database,
+            "UPDATE comments SET text = '<div><p>Ged, a <em>young</em> wizard, looses a \
+             shadow &amp; more.</p></div>' WHERE book = 1;",
+        )
+        .await
+        .unwrap();
+
+        let conn = connect_with_sidecar(
+            &database,
+            directory.path().join("anserno.db"),
+            sea_orm::ConnectOptions::new(""),
+            DatabaseAccess::Immutable,
+        )
+        .await
+        .unwrap();
+        let custom_columns = CustomColumns::load(&conn).await.unwrap();
+        SearchIndexUpdate::execute(&conn, &custom_columns)
+            .await
+            .unwrap();
+
+        let highlights = SearchHighlight::find(&conn, &"em shadow".parse().unwrap(), &[1, 2])
+            .await
+            .unwrap();
+
+        assert_eq!(highlights.len(), 0);
+
+        let highlights = SearchHighlight::find(&conn, &"shadow".parse().unwrap(), &[1, 2])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            plain_text(&highlights[&1].snippet),
+            "Ged, a young wizard, looses a shadow & more."
+        );
+    }
+}
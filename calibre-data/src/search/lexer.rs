@@ -0,0 +1,142 @@
+use crate::error::{Error, Result};
+
+/// Lexical token of a Calibre search expression.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub enum Token {
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+    Term {
+        field: Option<String>,
+        value: String,
+        quoted: bool,
+    },
+}
+
+impl ::std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Not => write!(f, "NOT"),
+            Self::LeftParen => write!(f, "("),
+            Self::RightParen => write!(f, ")"),
+            Self::Term {
+                field,
+                value,
+                quoted,
+            } => {
+                if let Some(field) = field {
+                    write!(f, "{field}:")?;
+                }
+
+                match quoted {
+                    true => write!(f, "{value:?}"),
+                    false => write!(f, "{value}"),
+                }
+            }
+        }
+    }
+}
+
+/// Split a Calibre search expression into tokens.
+///
+/// Terms are either bare words, `"quoted phrases"`, or `field:value` pairs
+/// where the value may itself be quoted, e.g. `authors:"=Le Guin"`.
+pub fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+
+            '"' => {
+                chars.next();
+                tokens.push(Token::Term {
+                    field: None,
+                    value: quoted(&mut chars)?,
+                    quoted: true,
+                });
+            }
+
+            _ => {
+                let mut word = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                if let Some(field) = word
+                    .strip_suffix(':')
+                    .filter(|_| chars.peek() == Some(&'"'))
+                {
+                    chars.next();
+                    tokens.push(Token::Term {
+                        field: Some(field.to_lowercase()),
+                        value: quoted(&mut chars)?,
+                        quoted: true,
+                    });
+                    continue;
+                }
+
+                tokens.push(match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => match word.split_once(':') {
+                        Some((field, value)) if !field.is_empty() => Token::Term {
+                            field: Some(field.to_lowercase()),
+                            value: value.to_string(),
+                            quoted: false,
+                        },
+                        _ => Token::Term {
+                            field: None,
+                            value: word,
+                            quoted: false,
+                        },
+                    },
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Consume a quoted string whose opening quote has already been read.
+fn quoted(chars: &mut ::std::iter::Peekable<::std::str::Chars>) -> Result<String> {
+    let mut value = String::new();
+
+    loop {
+        match chars.next() {
+            Some('\\') => match chars.next() {
+                Some(c) => value.push(c),
+                None => break,
+            },
+            Some('"') => return Ok(value),
+            Some(c) => value.push(c),
+            None => break,
+        }
+    }
+
+    Err(Error::Search(format!("Unterminated quote in: \"{value}")))
+}
@@ -0,0 +1,130 @@
+/// Tags ending a line of text, replaced by a space so the words on either
+/// side stay apart.
+const BREAKING_TAGS: &[&str] = &[
+    "address",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dt",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "p",
+    "td",
+    "th",
+    "tr",
+];
+
+/// Text of the html `markup` Calibre stores comments as, with tags removed,
+/// character references decoded and whitespace collapsed.
+pub fn strip_markup(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut rest = markup;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let consumed = if rest.starts_with('<') {
+            tag(rest).map(|(length, name)| {
+                if BREAKING_TAGS.contains(&name.to_ascii_lowercase().as_str()) {
+                    text.push(' ');
+                }
+                length
+            })
+        } else {
+            character_reference(rest).map(|(length, c)| {
+                text.push(c);
+                length
+            })
+        };
+
+        // Anything else is text which happens to hold a `<` or `&`.
+        let consumed = consumed.unwrap_or_else(|| {
+            text.push(rest.chars().next().unwrap_or_default());
+            1
+        });
+
+        rest = &rest[consumed..];
+    }
+
+    text.push_str(rest);
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Length and name of the tag, comment or declaration `rest` starts with.
+fn tag(rest: &str) -> Option<(usize, &str)> {
+    let inner = &rest[1..];
+
+    if !inner.starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')) {
+        return None;
+    }
+
+    let length = if inner.starts_with("!--") {
+        inner.find("-->")? + 1 + "-->".len()
+    } else {
+        inner.find('>')? + 2
+    };
+
+    let name = inner.trim_start_matches('/');
+    let name = &name[..name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len())];
+
+    Some((length, name))
+}
+
+/// Length and character of the character reference `rest` starts with.
+fn character_reference(rest: &str) -> Option<(usize, char)> {
+    let (end, _) = rest.char_indices().take(12).find(|&(_, c)| c == ';')?;
+    let reference = &rest[1..end];
+
+    let c = match reference {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = match reference.strip_prefix('#')? {
+                hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16),
+                decimal => decimal.parse(),
+            };
+            char::from_u32(code.ok()?)?
+        }
+    };
+
+    Some((end + 1, c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_tags_and_references() {
+        assert_eq!(
+            strip_markup(
+                "<div><p>Ged, a <em>young</em> wizard.</p><p>Tenar &amp; the Tombs&#8230;</p>\
+                 <!-- <p>hidden</p> --><br/>Earth&#x73;ea</div>"
+            ),
+            "Ged, a young wizard. Tenar & the Tombs\u{2026} Earthsea"
+        );
+    }
+
+    #[test]
+    fn keeps_text_resembling_markup() {
+        assert_eq!(
+            strip_markup("1 < 2 & 3 > 2, <3 &unknown; <unclosed"),
+            "1 < 2 & 3 > 2, <3 &unknown; <unclosed"
+        );
+    }
+}
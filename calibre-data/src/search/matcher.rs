@@ -0,0 +1,73 @@
+use sea_orm::sea_query::{BinOper, Expr, LikeExpr, SimpleExpr};
+
+use crate::{
+    error::{Error, Result},
+    search::{DateRange, Operator},
+};
+
+/// How a term value is compared against a field.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub enum Matcher {
+    /// Case-insensitive substring match, the Calibre default.
+    Contains(String),
+
+    /// Case-insensitive equality, written as `field:=value`.
+    Exact(String),
+
+    /// Case-insensitive regular expression, written as `field:~pattern`.
+    Regex(String),
+
+    /// Any non-empty value, written as `field:true`.
+    Present,
+
+    /// Numeric comparison, written as `field:>=value`.
+    Number(Operator, f64),
+
+    /// Date comparison against a period, written as `field:<2010-06`.
+    Date(Operator, DateRange),
+}
+
+impl Matcher {
+    /// Parse a text term value, `=` and `~` prefixes select exact and
+    /// regular expression matches.
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Some(value) = value.strip_prefix('=') {
+            return Ok(Self::Exact(value.to_string()));
+        }
+
+        if let Some(pattern) = value.strip_prefix('~') {
+            return regex::Regex::new(pattern)
+                .map(|_| Self::Regex(pattern.to_string()))
+                .map_err(|err| {
+                    Error::Search(format!("Invalid regular expression {pattern}: {err}"))
+                });
+        }
+
+        Ok(Self::Contains(value.to_string()))
+    }
+
+    /// Boolean expression comparing `column` with this matcher.
+    pub fn condition(&self, column: Expr) -> SimpleExpr {
+        match self {
+            Self::Contains(value) => {
+                column.like(LikeExpr::new(format!("%{}%", escape_like(value))).escape('\\'))
+            }
+            Self::Exact(value) => column.like(LikeExpr::new(escape_like(value)).escape('\\')),
+            Self::Regex(pattern) => column.binary(
+                BinOper::Custom("REGEXP"),
+                Expr::val(format!("(?i){pattern}")),
+            ),
+            Self::Present => column.clone().is_not_null().and(column.ne("")),
+            Self::Number(operator, value) => operator.condition(column, *value),
+            Self::Date(operator, range) => range.condition(*operator, column),
+        }
+    }
+}
+
+/// Escape the `LIKE` wildcards of a literal value.
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
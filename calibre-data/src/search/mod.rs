@@ -0,0 +1,29 @@
+mod lexer;
+pub use lexer::*;
+
+mod comparison;
+pub use comparison::*;
+
+mod expression;
+pub use expression::*;
+
+mod field;
+pub use field::*;
+
+mod matcher;
+pub use matcher::*;
+
+mod full_text;
+pub use full_text::*;
+
+mod highlight;
+pub use highlight::*;
+
+mod markup;
+pub use markup::*;
+
+mod ranking;
+pub use ranking::*;
+
+mod suggestion;
+pub use suggestion::*;
@@ -0,0 +1,107 @@
+use sea_orm::sea_query::{Expr, SimpleExpr};
+
+use crate::error::{Error, Result};
+
+/// Weights of the search index columns when ranking full text search hits
+/// with `bm25()`, a hit in a heavier column counts for more.
+#[derive(::core::marker::Copy, ::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq)]
+pub struct SearchWeights {
+    pub title: f64,
+    pub sort: f64,
+    pub authors: f64,
+    pub series: f64,
+    pub formats: f64,
+    pub tags: f64,
+    pub description: f64,
+    pub custom: f64,
+}
+
+impl ::std::default::Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            title: 10.0,
+            // The title sort mostly repeats the title.
+            sort: 0.0,
+            authors: 8.0,
+            series: 5.0,
+            formats: 1.0,
+            tags: 3.0,
+            description: 1.0,
+            custom: 1.0,
+        }
+    }
+}
+
+impl SearchWeights {
+    /// Set the weight of a search index column by name.
+    pub fn with_weight(self, column: &str, weight: f64) -> Result<Self> {
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(Error::Search(format!(
+                "Search weight of {column} must be a positive number, found: {weight}"
+            )));
+        }
+
+        let mut weights = self;
+
+        *match column {
+            "title" => &mut weights.title,
+            "sort" => &mut weights.sort,
+            "authors" => &mut weights.authors,
+            "series" => &mut weights.series,
+            "formats" => &mut weights.formats,
+            "tags" => &mut weights.tags,
+            "description" => &mut weights.description,
+            "custom" => &mut weights.custom,
+            _ => {
+                return Err(Error::Search(format!(
+                    "Unknown search index column: {column}, expected one of title, sort, \
+                     authors, series, formats, tags, description or custom"
+                )))
+            }
+        } = weight;
+
+        Ok(weights)
+    }
+
+    /// `bm25()` rank of the current full text search hit, lower ranks match
+    /// better.
+    pub fn rank(&self) -> SimpleExpr {
+        // Weights are given in the order of the search index columns.
+        let weights = [
+            self.title,
+            self.sort,
+            self.authors,
+            self.series,
+            self.formats,
+            self.tags,
+            self.description,
+            self.custom,
+        ];
+
+        Expr::cust_with_values(
+            format!(
+                r#"bm25("anserno_search_index", {})"#,
+                vec!["?"; weights.len()].join(", ")
+            ),
+            weights,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn with_weight() {
+        let weights = SearchWeights::default()
+            .with_weight("description", 4.0)
+            .unwrap();
+
+        assert_eq!(weights.description, 4.0);
+        assert_eq!(weights.title, SearchWeights::default().title);
+
+        assert!(SearchWeights::default().with_weight("isbn", 1.0).is_err());
+        assert!(SearchWeights::default().with_weight("title", -1.0).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use sea_orm::{ConnectionTrait, Statement};
+
+use crate::error::{Error, Result};
+
+/// What a suggestion completes to.
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    ::std::cmp::Eq,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    Title,
+    Author,
+    Series,
+}
+
+impl ::std::str::FromStr for SuggestionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "title" => Ok(Self::Title),
+            "author" => Ok(Self::Author),
+            "series" => Ok(Self::Series),
+            _ => Err(Error::Search(format!("Unknown suggestion kind: {s}"))),
+        }
+    }
+}
+
+/// Title, author or series completing what was typed in the search box.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::cmp::PartialEq, serde::Serialize)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// Id of the book, author or series
+    pub id: i32,
+    pub value: String,
+    /// Number of books of the author or series, 1 for titles
+    pub books: i64,
+}
+
+impl Suggestion {
+    /// Up to `limit` suggestions containing every word of `input`, the last
+    /// one completed as a prefix unless followed by a space, best matches
+    /// first and then those with the most books.
+    pub async fn find<C>(conn: &C, input: &str, limit: u64) -> Result<Vec<Self>>
+    where
+        C: ConnectionTrait,
+    {
+        let Some(query) = suggest_query(input) else {
+            return Ok(Vec::new());
+        };
+
+        conn.query_all(Statement::from_sql_and_values(
+            conn.get_database_backend(),
+            indoc::indoc! {r#"
+                SELECT "kind", "id", "value", "books"
+                FROM "anserno_suggest_index"
+                WHERE "anserno_suggest_index" MATCH ?
+                ORDER BY "rank", "books" DESC
+                LIMIT ?
+            "#},
+            [query.into(), limit.into()],
+        ))
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Self {
+                kind: row.try_get_by_index::<String>(0)?.parse()?,
+                id: row.try_get_by_index(1)?,
+                value: row.try_get_by_index(2)?,
+                books: row.try_get_by_index(3)?,
+            })
+        })
+        .collect()
+    }
+}
+
+/// fts5 query matching every word of `input` as a string, the last one as a
+/// prefix unless followed by a space, or `None` without any word.
+fn suggest_query(input: &str) -> Option<String> {
+    let words = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+
+    let (last, words) = words.split_last()?;
+
+    let prefix = match input.ends_with(|c: char| c.is_alphanumeric()) {
+        true => " *",
+        false => "",
+    };
+
+    Some(
+        words
+            .iter()
+            .map(|word| format!("\"{word}\""))
+            .chain([format!("\"{last}\"{prefix}")])
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn suggest_queries() {
+        assert_eq!(suggest_query("le gu").as_deref(), Some(r#""le" "gu" *"#));
+        assert_eq!(suggest_query("Le Guin ").as_deref(), Some(r#""Le" "Guin""#));
+        assert_eq!(
+            suggest_query(r#"o"brien's"#).as_deref(),
+            Some(r#""o" "brien" "s" *"#)
+        );
+        assert_eq!(suggest_query(" ,. "), None);
+    }
+}
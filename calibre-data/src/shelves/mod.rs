@@ -0,0 +1,2 @@
+mod shelf;
+pub use shelf::*;
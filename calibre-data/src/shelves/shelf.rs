@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use sea_orm::{sea_query::SimpleExpr, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+use crate::{
+    custom_columns::CustomColumns,
+    entities::preferences,
+    error::{Error, Result},
+    search::Expression,
+};
+
+/// Preference key holding the saved searches of a library.
+pub const SAVED_SEARCHES_KEY: &str = "saved_searches";
+
+/// Preference key holding the virtual library definitions of a library.
+pub const VIRTUAL_LIBRARIES_KEY: &str = "virtual_libraries";
+
+#[derive(
+    ::core::marker::Copy,
+    ::std::clone::Clone,
+    ::std::fmt::Debug,
+    ::std::cmp::PartialEq,
+    serde::Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ShelfKind {
+    SavedSearch,
+    VirtualLibrary,
+}
+
+/// Named search expression curated in Calibre, either a saved search or a
+/// virtual library.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, serde::Serialize)]
+pub struct Shelf {
+    pub name: String,
+    pub kind: ShelfKind,
+    pub search: String,
+}
+
+/// Shelves defined in the preferences of a Calibre library.
+#[derive(::std::clone::Clone, ::std::fmt::Debug, ::std::default::Default, serde::Serialize)]
+pub struct Shelves {
+    saved_searches: BTreeMap<String, String>,
+    shelves: Vec<Shelf>,
+}
+
+impl Shelves {
+    /// Read the saved searches and virtual libraries from the library
+    /// database. Virtual libraries are listed first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn load(conn: &DatabaseConnection) -> Result<Self> {
+        let saved_searches = preference(conn, SAVED_SEARCHES_KEY).await?;
+        let virtual_libraries = preference(conn, VIRTUAL_LIBRARIES_KEY).await?;
+
+        let shelves = virtual_libraries
+            .iter()
+            .map(|(name, search)| (name, search, ShelfKind::VirtualLibrary))
+            .chain(
+                saved_searches
+                    .iter()
+                    .map(|(name, search)| (name, search, ShelfKind::SavedSearch)),
+            )
+            .map(|(name, search, kind)| Shelf {
+                name: name.clone(),
+                kind,
+                search: search.clone(),
+            })
+            .collect();
+
+        Ok(Self {
+            saved_searches,
+            shelves,
+        })
+    }
+
+    /// Read only the saved searches from the library database, by name, for
+    /// resolving `search:name` references.
+    pub async fn load_saved_searches(
+        conn: &DatabaseConnection,
+    ) -> Result<BTreeMap<String, String>> {
+        preference(conn, SAVED_SEARCHES_KEY).await
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Shelf> {
+        self.shelves.iter()
+    }
+
+    /// Lookup a shelf by name, virtual libraries shadow saved searches.
+    pub fn get(&self, name: &str) -> Option<&Shelf> {
+        self.shelves.iter().find(|shelf| shelf.name == name)
+    }
+
+    /// Condition selecting the books of `shelf` by their id `column`.
+    pub fn condition<C>(
+        &self,
+        shelf: &Shelf,
+        column: C,
+        custom_columns: &CustomColumns,
+    ) -> Result<SimpleExpr>
+    where
+        C: ColumnTrait,
+    {
+        self.search_condition(&shelf.search, column, custom_columns)
+    }
+
+    /// Condition selecting the books matching the Calibre search expression
+    /// `search` by their id `column`, resolving saved searches and the
+    /// `custom_columns` of the library.
+    pub fn search_condition<C>(
+        &self,
+        search: &str,
+        column: C,
+        custom_columns: &CustomColumns,
+    ) -> Result<SimpleExpr>
+    where
+        C: ColumnTrait,
+    {
+        search
+            .parse::<Expression>()?
+            .condition(column, &self.saved_searches, custom_columns)
+    }
+}
+
+/// Read a JSON object of names to search expressions from the preferences
+/// table, missing keys read as empty.
+async fn preference(conn: &DatabaseConnection, key: &str) -> Result<BTreeMap<String, String>> {
+    let Some(model) = preferences::Entity::find()
+        .filter(preferences::Column::Key.eq(key))
+        .one(conn)
+        .await?
+    else {
+        return Ok(BTreeMap::new());
+    };
+
+    serde_json::from_str(&model.val)
+        .map_err(|err| Error::Search(format!("Failed parsing preference {key}: {err}")))
+}
@@ -0,0 +1,60 @@
+-- Calibre library of three books, with custom columns, saved searches,
+-- virtual libraries and viewer annotations.
+PRAGMA user_version = 26;
+CREATE TABLE books ( id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL DEFAULT 'Unknown' COLLATE NOCASE, sort TEXT COLLATE NOCASE, timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP, pubdate TIMESTAMP DEFAULT CURRENT_TIMESTAMP, series_index REAL NOT NULL DEFAULT 1.0, author_sort TEXT COLLATE NOCASE, isbn TEXT DEFAULT "" COLLATE NOCASE, lccn TEXT DEFAULT "" COLLATE NOCASE, path TEXT NOT NULL DEFAULT "", flags INTEGER NOT NULL DEFAULT 1, uuid TEXT, has_cover BOOL DEFAULT 0, last_modified TIMESTAMP NOT NULL DEFAULT "2000-01-01 00:00:00+00:00");
+CREATE TABLE authors ( id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE(name));
+CREATE TABLE books_authors_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, author INTEGER NOT NULL, UNIQUE(book, author));
+CREATE TABLE series ( id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE (name));
+CREATE TABLE books_series_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, series INTEGER NOT NULL, UNIQUE(book));
+CREATE TABLE tags ( id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE (name));
+CREATE TABLE books_tags_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, tag INTEGER NOT NULL, UNIQUE(book, tag));
+CREATE TABLE publishers ( id INTEGER PRIMARY KEY, name TEXT NOT NULL COLLATE NOCASE, sort TEXT COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE(name));
+CREATE TABLE books_publishers_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, publisher INTEGER NOT NULL, UNIQUE(book));
+CREATE TABLE languages ( id INTEGER PRIMARY KEY, lang_code TEXT NOT NULL COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE(lang_code));
+CREATE TABLE books_languages_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, lang_code INTEGER NOT NULL, item_order INTEGER NOT NULL DEFAULT 0, UNIQUE(book, lang_code));
+CREATE TABLE ratings ( id INTEGER PRIMARY KEY, rating INTEGER CHECK(rating > -1 AND rating < 11), link TEXT NOT NULL DEFAULT "", UNIQUE (rating));
+CREATE TABLE books_ratings_link ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, rating INTEGER NOT NULL, UNIQUE(book, rating));
+CREATE TABLE identifiers ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, type TEXT NOT NULL DEFAULT "isbn" COLLATE NOCASE, val TEXT NOT NULL COLLATE NOCASE, UNIQUE(book, type));
+CREATE TABLE comments ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, text TEXT NOT NULL COLLATE NOCASE, UNIQUE(book));
+CREATE TABLE data ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, format TEXT NOT NULL COLLATE NOCASE, uncompressed_size INTEGER NOT NULL, name TEXT NOT NULL, UNIQUE(book, format));
+CREATE TABLE preferences(id INTEGER PRIMARY KEY, key TEXT NOT NULL, val TEXT NOT NULL, UNIQUE(key));
+CREATE TABLE custom_columns ( id INTEGER PRIMARY KEY AUTOINCREMENT, label TEXT NOT NULL, name TEXT NOT NULL, datatype TEXT NOT NULL, mark_for_delete BOOL DEFAULT 0 NOT NULL, editable BOOL DEFAULT 1 NOT NULL, display TEXT DEFAULT "{}" NOT NULL, is_multiple BOOL DEFAULT 0 NOT NULL, normalized BOOL NOT NULL, UNIQUE(label));
+CREATE TABLE annotations ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, format TEXT NOT NULL COLLATE NOCASE, user_type TEXT NOT NULL, user TEXT NOT NULL, timestamp REAL NOT NULL, annot_id TEXT NOT NULL, annot_type TEXT NOT NULL, annot_data TEXT NOT NULL, searchable_text TEXT NOT NULL DEFAULT "", UNIQUE(book, user_type, user, format, annot_type, annot_id));
+CREATE TABLE last_read_positions ( id INTEGER PRIMARY KEY, book INTEGER NOT NULL, format TEXT NOT NULL COLLATE NOCASE, user TEXT NOT NULL, device TEXT NOT NULL, cpos TEXT NOT NULL, pos_frac REAL NOT NULL DEFAULT 0, epoch REAL NOT NULL, UNIQUE(user, device, book, format));
+CREATE TABLE custom_column_1(id INTEGER PRIMARY KEY AUTOINCREMENT, value TEXT NOT NULL COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE(value));
+CREATE TABLE books_custom_column_1_link(id INTEGER PRIMARY KEY AUTOINCREMENT, book INTEGER NOT NULL, value INTEGER NOT NULL, UNIQUE(book, value));
+CREATE TABLE custom_column_2(id INTEGER PRIMARY KEY AUTOINCREMENT, book INTEGER, value INTEGER NOT NULL, UNIQUE(book));
+CREATE TABLE custom_column_3(id INTEGER PRIMARY KEY AUTOINCREMENT, book INTEGER, value BOOL NOT NULL, UNIQUE(book));
+CREATE TABLE custom_column_4(id INTEGER PRIMARY KEY AUTOINCREMENT, value TEXT NOT NULL COLLATE NOCASE, link TEXT NOT NULL DEFAULT "", UNIQUE(value));
+CREATE TABLE books_custom_column_4_link(id INTEGER PRIMARY KEY AUTOINCREMENT, book INTEGER NOT NULL, value INTEGER NOT NULL, extra REAL, UNIQUE(book, value));
+CREATE TABLE custom_column_5(id INTEGER PRIMARY KEY AUTOINCREMENT, book INTEGER, value TIMESTAMP NOT NULL, UNIQUE(book));
+INSERT INTO custom_columns VALUES (1,'shelf','Shelf','text',0,1,'{}',1,1),(2,'pages','Pages','int',0,1,'{}',0,0),(3,'read','Read','bool',0,1,'{}',0,0),(4,'arc','Arc','series',0,1,'{}',0,1),(5,'finished','Finished','datetime',0,1,'{}',0,0);
+INSERT INTO custom_column_1(id,value) VALUES (1,'Living room'),(2,'Attic');
+INSERT INTO books_custom_column_1_link(book,value) VALUES (1,1),(1,2),(2,1);
+INSERT INTO custom_column_2(book,value) VALUES (1,300),(2,150);
+INSERT INTO custom_column_3(book,value) VALUES (1,1),(3,0);
+INSERT INTO custom_column_4(id,value) VALUES (1,'Early');
+INSERT INTO books_custom_column_4_link(book,value,extra) VALUES (1,1,2.0);
+INSERT INTO custom_column_5(book,value) VALUES (1,'2023-05-01 10:00:00+00:00');
+INSERT INTO books(id,title,sort,timestamp,pubdate,series_index,author_sort,isbn,path,uuid,has_cover,last_modified) VALUES
+ (1,'A Wizard of Earthsea','Wizard of Earthsea, A','2024-01-02 03:04:05+00:00','1968-11-01 00:00:00+00:00',1.0,'Le Guin, Ursula K.','9780547773742','Ursula K. Le Guin/A Wizard of Earthsea (1)','aaaaaaaa-0000-0000-0000-000000000001',1,'2024-02-01 00:00:00+00:00'),
+ (2,'The Tombs of Atuan','Tombs of Atuan, The','2024-01-03 03:04:05+00:00','1971-01-01 00:00:00+00:00',2.0,'Le Guin, Ursula K.','','Ursula K. Le Guin/The Tombs of Atuan (2)','aaaaaaaa-0000-0000-0000-000000000002',1,'2024-02-02 00:00:00+00:00'),
+ (3,'Der Process','Process, Der','2024-01-04 03:04:05+00:00','1925-01-01 00:00:00+00:00',1.0,'Kafka, Franz','','Franz Kafka/Der Process (3)','aaaaaaaa-0000-0000-0000-000000000003',0,'2024-02-03 00:00:00+00:00');
+INSERT INTO authors VALUES (1,'Ursula K. Le Guin','Le Guin, Ursula K.',''),(2,'Franz Kafka','Kafka, Franz','');
+INSERT INTO books_authors_link(book,author) VALUES (1,1),(2,1),(3,2);
+INSERT INTO series VALUES (1,'Earthsea','Earthsea','');
+INSERT INTO books_series_link(book,series) VALUES (1,1),(2,1);
+INSERT INTO tags VALUES (1,'Fantasy',''),(2,'Classic',''),(3,'read','');
+INSERT INTO books_tags_link(book,tag) VALUES (1,1),(2,1),(1,2),(3,2),(1,3);
+INSERT INTO publishers VALUES (1,'Parnassus Press','Parnassus Press',''),(2,'Die Schmiede','Schmiede, Die','');
+INSERT INTO books_publishers_link(book,publisher) VALUES (1,1),(2,1),(3,2);
+INSERT INTO languages VALUES (1,'eng',''),(2,'deu','');
+INSERT INTO books_languages_link(book,lang_code,item_order) VALUES (1,1,0),(2,1,0),(3,2,0);
+INSERT INTO ratings VALUES (1,10,''),(2,8,''),(3,6,'');
+INSERT INTO books_ratings_link(book,rating) VALUES (1,1),(2,2),(3,3);
+INSERT INTO identifiers(book,type,val) VALUES (1,'isbn','9780547773742'),(1,'goodreads','13642'),(3,'isbn','9783518380277');
+INSERT INTO comments(book,text) VALUES (1,'<p>Ged, a young wizard, looses a shadow upon the world.</p>'),(2,'<p>Tenar is the priestess of the Tombs.</p>');
+INSERT INTO data(book,format,uncompressed_size,name) VALUES (1,'EPUB',12345,'A Wizard of Earthsea - Ursula K. Le Guin'),(1,'PDF',98765,'A Wizard of Earthsea - Ursula K. Le Guin'),(2,'EPUB',11111,'The Tombs of Atuan - Ursula K. Le Guin'),(3,'EPUB',22222,'Der Process - Franz Kafka');
+INSERT INTO preferences(key,val) VALUES ('saved_searches','{"Le Guin": "authors:\"=Ursula K. Le Guin\"", "Unread": "not tags:read"}'),('virtual_libraries','{"Classics": "tags:Classic"}');
+INSERT INTO annotations(book,format,user_type,user,timestamp,annot_id,annot_type,annot_data,searchable_text) VALUES (1,'EPUB','local','viewer',1700000000.0,'h1','highlight','{"type":"highlight","uuid":"h1","highlighted_text":"To light a candle is to cast a shadow","notes":"Key theme","toc_family_titles":["Chapter 3"],"timestamp":"2023-11-14T22:13:20.000Z","style":{"kind":"color","which":"yellow"}}','To light a candle is to cast a shadow'),(1,'EPUB','local','viewer',1700000100.0,'b1','bookmark','{"type":"bookmark","title":"Roke","pos":"epubcfi(/6/4)","timestamp":"2023-11-14T22:15:00.000Z"}','Roke');
+INSERT INTO last_read_positions(book,format,user,device,cpos,pos_frac,epoch) VALUES (1,'EPUB','_','_','epubcfi(/6/8)',0.42,1700000200.0);
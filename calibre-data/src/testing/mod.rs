@@ -0,0 +1,37 @@
+//! Calibre library fixtures for tests, enabled by the `test-util` feature.
+
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use sea_orm::{ConnectionTrait, Database};
+
+use crate::error::Result;
+
+/// Characters escaped in the database path of a database url.
+const PATH_ESCAPED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'/');
+
+/// Statements creating a Calibre 7 `metadata.db` with three books.
+pub const METADATA_SQL: &str = include_str!("metadata.sql");
+
+/// Create a library in `directory` whose `metadata.db` is built from
+/// `METADATA_SQL`, returning the path of the database.
+pub async fn create_library(directory: &Path) -> Result<PathBuf> {
+    let database = directory.join("metadata.db");
+
+    execute_sql(&database, METADATA_SQL).await?;
+
+    Ok(database)
+}
+
+/// Run `sql` against the sqlite `database`, creating it if missing.
+pub async fn execute_sql(database: &Path, sql: &str) -> Result<()> {
+    // Paths are percent-decoded from database urls.
+    let path = percent_encoding::utf8_percent_encode(&database.to_string_lossy(), PATH_ESCAPED)
+        .to_string();
+    let conn = Database::connect(format!("sqlite://{path}?mode=rwc")).await?;
+
+    conn.execute_unprepared(sql).await?;
+    conn.close().await?;
+
+    Ok(())
+}